@@ -1,46 +1,402 @@
+use super::fft_detector::FftDetector;
 use super::pitch_detector;
 use circular_queue::CircularQueue;
 use std::option::*;
 use wasm_bindgen::prelude::*;
 
+// Enough for two of the largest windows.
 const CAPACITY: usize = pitch_detector::MAX_WINDOW_SIZE * 2;
 
+pub const DEFAULT_HIGH_PASS_CUTOFF_HZ: f32 = 40.0;
+
+// First-order high-pass filter for removing DC offset and low rumble. Keeps the previous sample
+// so consecutive chunks are filtered without a discontinuity at the boundary.
+struct HighPassFilter {
+  alpha: f32,
+  previous_input: f32,
+  previous_output: f32,
+}
+
+impl HighPassFilter {
+  fn new(cutoff_hz: f32, sample_rate: usize) -> HighPassFilter {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff_hz);
+    let dt = 1.0 / sample_rate as f32;
+
+    HighPassFilter {
+      alpha: rc / (rc + dt),
+      previous_input: 0.0,
+      previous_output: 0.0,
+    }
+  }
+
+  fn reset(&mut self) {
+    self.previous_input = 0.0;
+    self.previous_output = 0.0;
+  }
+
+  fn process(&mut self, input: f32) -> f32 {
+    let output = self.alpha * (self.previous_output + input - self.previous_input);
+    self.previous_input = input;
+    self.previous_output = output;
+    output
+  }
+}
+
+// Smoothed signal level for metering: moves a fraction of the way towards each new chunk's RMS
+// level, quickly (attack) when the level rises and more slowly (release) when it falls.
+pub struct EnvelopeFollower {
+  attack: f32,
+  release: f32,
+  value: f32,
+}
+
+impl EnvelopeFollower {
+  // Coefficients between 0 (never move) and 1 (jump straight to the new level).
+  pub fn new(attack: f32, release: f32) -> EnvelopeFollower {
+    EnvelopeFollower {
+      attack,
+      release,
+      value: 0.0,
+    }
+  }
+
+  pub fn update(&mut self, level: f32) -> f32 {
+    let coefficient = match level > self.value {
+      true => self.attack,
+      false => self.release,
+    };
+    self.value += coefficient * (level - self.value);
+    self.value
+  }
+
+  pub fn value(&self) -> f32 {
+    self.value
+  }
+}
+
+// Fraction (0..1) of consecutive sample pairs that change sign. Low for pitched sounds, high for
+// noise.
+fn zero_crossing_rate(samples: &[f32]) -> f32 {
+  if samples.len() < 2 {
+    return 0.0;
+  }
+
+  let crossings = samples
+    .windows(2)
+    .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+    .count();
+
+  crossings as f32 / (samples.len() - 1) as f32
+}
+
+// Largest magnitude of the samples.
+fn peak_of<'a>(samples: impl Iterator<Item = &'a f32>) -> f32 {
+  samples.fold(0.0_f32, |peak, sample| peak.max(sample.abs()))
+}
+
+// Scales samples so the largest magnitude equals target_peak. Silence is left untouched.
+fn normalize_to_peak(samples: &mut [f32], target_peak: f32) {
+  let peak = peak_of(samples.iter());
+  if peak == 0.0 {
+    return;
+  }
+
+  let gain = target_peak / peak;
+  for sample in samples.iter_mut() {
+    *sample *= gain;
+  }
+}
+
 #[wasm_bindgen]
 pub struct AudioSamplesProcessor {
   pub chunk_size: usize,
+  // When set, samples handed to detectors are scaled so the peak over the stored buffer reaches
+  // target_peak. Keeps quiet input above the detectors' power threshold regardless of gain.
+  pub normalize: bool,
+  pub target_peak: f32,
   time_of_last_added_sample: usize,
+  high_pass_filter: Option<HighPassFilter>,
+  envelope_follower: EnvelopeFollower,
   recent_audio_sample_f32s: CircularQueue<f32>,
 }
 
 #[wasm_bindgen]
 impl AudioSamplesProcessor {
   pub fn new() -> AudioSamplesProcessor {
+    AudioSamplesProcessor::with_chunk_size(128)
+  }
+
+  // For audio backends that deliver buffers of other sizes than the Web Audio default of 128.
+  pub fn with_chunk_size(chunk_size: usize) -> AudioSamplesProcessor {
+    AudioSamplesProcessor::with_chunk_size_and_capacity(chunk_size, CAPACITY)
+  }
+
+  // Buffers just two of the given window rather than two of the largest supported window, so
+  // small-window detectors use less memory and analyze fewer samples per call.
+  pub fn for_window(window: usize) -> AudioSamplesProcessor {
+    assert!(
+      window > 0 && window <= pitch_detector::MAX_WINDOW_SIZE,
+      "AudioSamplesProcessor window must be between 1 and {}, got {}",
+      pitch_detector::MAX_WINDOW_SIZE,
+      window
+    );
+
+    AudioSamplesProcessor::with_chunk_size_and_capacity(128, window * 2)
+  }
+
+  // Like for_window(), but sized for the samples each window spans after downsampling, so the
+  // buffer holds two full windows however the detector is configured.
+  pub fn for_params(params: pitch_detector::Params) -> AudioSamplesProcessor {
+    let samples_per_window = params.window * params.downsample_factor();
+    assert!(
+      samples_per_window > 0 && samples_per_window <= pitch_detector::MAX_WINDOW_SIZE,
+      "AudioSamplesProcessor window must span between 1 and {} samples, got {}",
+      pitch_detector::MAX_WINDOW_SIZE,
+      samples_per_window
+    );
+
+    AudioSamplesProcessor::with_chunk_size_and_capacity(128, samples_per_window * 2)
+  }
+
+  fn with_chunk_size_and_capacity(chunk_size: usize, capacity: usize) -> AudioSamplesProcessor {
+    assert!(
+      chunk_size > 0,
+      "AudioSamplesProcessor chunk size must be greater than 0"
+    );
+
     AudioSamplesProcessor {
-      chunk_size: 128,
+      chunk_size,
+
+      normalize: false,
+      target_peak: 1.0,
 
       time_of_last_added_sample: 0,
 
-      recent_audio_sample_f32s: CircularQueue::with_capacity(CAPACITY),
+      high_pass_filter: None,
+
+      envelope_follower: EnvelopeFollower::new(0.5, 0.1),
+
+      recent_audio_sample_f32s: CircularQueue::with_capacity(capacity),
     }
   }
 
+  // Accepts any non-zero multiple of chunk_size samples, so backends can deliver several chunks
+  // at once.
   pub fn add_samples_chunk(&mut self, sample_f32s: Vec<f32>) {
-    if sample_f32s.len() != self.chunk_size {
-      panic!(format!(
-        "add_samples_chunk() requires {} samples, instead got {}",
+    let len = sample_f32s.len();
+    if !self.try_add_samples_chunk(sample_f32s) {
+      panic!(
+        "add_samples_chunk() requires a multiple of {} samples, instead got {}",
+        self.chunk_size, len
+      );
+    }
+  }
+
+  // Whether len samples make up one or more whole chunks. usize::is_multiple_of() needs a newer
+  // toolchain than the crate supports.
+  #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+  fn is_whole_chunks(&self, len: usize) -> bool {
+    len > 0 && len % self.chunk_size == 0
+  }
+
+  // Like add_samples_chunk(), but returns false (adding nothing) for a chunk of the wrong size
+  // rather than panicking, which would stop the wasm module. Callers can then compare against
+  // chunk_size and adapt.
+  pub fn try_add_samples_chunk(&mut self, sample_f32s: Vec<f32>) -> bool {
+    if !self.is_whole_chunks(sample_f32s.len()) {
+      return false;
+    }
+
+    self.time_of_last_added_sample += sample_f32s.len();
+    for chunk in sample_f32s.chunks(self.chunk_size) {
+      let mut sum_of_squares = 0.0;
+
+      for sample in chunk.iter() {
+        let sample = self.push_sample(*sample);
+        sum_of_squares += sample * sample;
+      }
+
+      self
+        .envelope_follower
+        .update((sum_of_squares / chunk.len() as f32).sqrt());
+    }
+
+    true
+  }
+
+  // Stores a sample after the high pass filter, if enabled, returning the stored value.
+  fn push_sample(&mut self, sample: f32) -> f32 {
+    let sample = match &mut self.high_pass_filter {
+      Some(filter) => filter.process(sample),
+      None => sample,
+    };
+    self.recent_audio_sample_f32s.push(sample);
+
+    sample
+  }
+
+  // Like add_samples_chunk(), but for a chunk whose first sample is at absolute_time, so a chunk
+  // the audio backend dropped doesn't shift the times of everything after it. Any gap since the
+  // last added sample is filled with silence to keep the stored samples contiguous. Panics, adding
+  // nothing, if absolute_time is before the end of the last added chunk or the chunk is the wrong
+  // size.
+  pub fn add_samples_chunk_at(&mut self, sample_f32s: Vec<f32>, absolute_time: usize) {
+    if absolute_time < self.time_of_last_added_sample {
+      panic!(
+        "add_samples_chunk_at() requires a time of at least {}, instead got {}",
+        self.time_of_last_added_sample, absolute_time
+      );
+    }
+    if !self.is_whole_chunks(sample_f32s.len()) {
+      panic!(
+        "add_samples_chunk_at() requires a multiple of {} samples, instead got {}",
         self.chunk_size,
         sample_f32s.len()
-      ));
+      );
     }
 
-    self.time_of_last_added_sample += sample_f32s.len();
-    for sample in sample_f32s.into_iter() {
-      self.recent_audio_sample_f32s.push(sample);
+    // Samples older than the buffer's capacity would be discarded straight away. The silence
+    // passes through the filter like any other samples so its state follows the gap.
+    let gap = absolute_time - self.time_of_last_added_sample;
+    for _ in 0..gap.min(self.recent_audio_sample_f32s.capacity()) {
+      self.push_sample(0.0);
+    }
+    self.time_of_last_added_sample = absolute_time;
+
+    self.add_samples_chunk(sample_f32s);
+  }
+
+  // Mixes the two channels of a stereo chunk down to mono by averaging before adding them. Each
+  // channel must be a non-zero multiple of chunk_size samples, and both must be the same length.
+  pub fn add_stereo_chunk(&mut self, left: Vec<f32>, right: Vec<f32>) {
+    if left.len() != right.len() {
+      panic!(
+        "add_stereo_chunk() requires channels of equal length, instead got {} and {}",
+        left.len(),
+        right.len()
+      );
+    }
+
+    self.add_samples_chunk(
+      left
+        .iter()
+        .zip(right.iter())
+        .map(|(l, r)| (l + r) / 2.0)
+        .collect(),
+    );
+  }
+
+  // Like add_samples_chunk(), for 16 bit PCM such as decoded from a WAV file. Samples are
+  // normalized to -1.0..1.0.
+  pub fn add_samples_chunk_i16(&mut self, samples: Vec<i16>) {
+    self.add_samples_chunk(
+      samples
+        .iter()
+        .map(|sample| *sample as f32 / 32768.0)
+        .collect(),
+    );
+  }
+
+  // Smoothed RMS level of the added chunks, for a level meter that decays gradually rather than
+  // jumping with each chunk.
+  pub fn envelope(&self) -> f32 {
+    self.envelope_follower.value()
+  }
+
+  // Fractions of the way the envelope moves towards each chunk's level when it is rising
+  // (attack) and falling (release). Defaults to 0.5 and 0.1.
+  pub fn set_envelope_coefficients(&mut self, attack: f32, release: f32) {
+    self.envelope_follower = EnvelopeFollower {
+      attack,
+      release,
+      value: self.envelope_follower.value,
+    };
+  }
+
+  // Filters subsequently added samples to remove DC offset and rumble below cutoff_hz
+  // (DEFAULT_HIGH_PASS_CUTOFF_HZ suits most instruments). Already stored samples are unchanged.
+  pub fn enable_high_pass_filter(&mut self, cutoff_hz: f32, sample_rate: usize) {
+    self.high_pass_filter = Some(HighPassFilter::new(cutoff_hz, sample_rate));
+  }
+
+  pub fn disable_high_pass_filter(&mut self) {
+    self.high_pass_filter = None;
+  }
+
+  // Discards all stored samples and restarts sample times from 0, e.g. when recording stops so
+  // the next recording doesn't start with stale audio. Filter and envelope settings are kept but
+  // their state is reset.
+  pub fn clear(&mut self) {
+    self.recent_audio_sample_f32s.clear();
+    self.time_of_last_added_sample = 0;
+
+    if let Some(filter) = &mut self.high_pass_filter {
+      filter.reset();
     }
+    self.envelope_follower.value = 0.0;
   }
 
   pub fn has_sufficient_samples(&self, detector: &pitch_detector::PitchDetector) -> bool {
-    self.recent_audio_sample_f32s.len() >= detector.params.window
+    self.recent_audio_sample_f32s.len() >= detector.samples_per_window()
+  }
+
+  // How full the sample buffer is, from 0.0 when empty to 1.0 once at capacity. Useful for
+  // showing buffering progress before the first pitches are available.
+  pub fn fill_fraction(&self) -> f32 {
+    let capacity = self.recent_audio_sample_f32s.capacity();
+
+    (self.recent_audio_sample_f32s.len() as f32 / capacity as f32).min(1.0)
+  }
+
+  // How much audio is buffered, for diagnostics. Whole chunks only, so a capacity that isn't a
+  // multiple of chunk_size rounds down.
+  #[wasm_bindgen(getter)]
+  pub fn stored_chunks(&self) -> usize {
+    self.stored_samples() / self.chunk_size
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn max_chunks(&self) -> usize {
+    self.recent_audio_sample_f32s.capacity() / self.chunk_size
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn stored_samples(&self) -> usize {
+    self.recent_audio_sample_f32s.len()
+  }
+
+  // Largest magnitude over the stored samples.
+  pub fn peak_amplitude(&self) -> f32 {
+    let mut peak = 0.0_f32;
+    self.for_each_sample(|sample| peak = peak.max(sample.abs()));
+    peak
+  }
+
+  // Whether any stored sample reaches the threshold magnitude, e.g. 0.99 to warn that the input
+  // is clipping and pitches will be unreliable.
+  pub fn is_clipping(&self, threshold: f32) -> bool {
+    self.peak_amplitude() >= threshold
+  }
+
+  // Zero-crossing rate of the most recent window samples, a cheap check for whether the input is
+  // noise (a high rate, around 0.5 for white noise) before running pitch detection.
+  pub fn zero_crossing_rate(&self, window: usize) -> f32 {
+    let samples = self.latest_samples();
+    let start = samples.len() - window.min(samples.len());
+
+    zero_crossing_rate(&samples[start..])
+  }
+
+  // Magnitude spectrum of the most recent window samples (Hann windowed), for drawing alongside
+  // the detected pitch. Bin i is at i * sample_rate / window Hz, up to the Nyquist frequency.
+  // Empty until a window of samples has been added.
+  pub fn magnitude_spectrum(&self, window: usize) -> Vec<f32> {
+    let samples = self.latest_samples();
+    if window == 0 || samples.len() < window {
+      return vec![];
+    }
+
+    FftDetector::new(window, 0).magnitude_spectrum(&samples[samples.len() - window..])
   }
 
   pub fn create_pitch_detector(
@@ -62,26 +418,104 @@ impl AudioSamplesProcessor {
     ))
   }
 
+  // Creates one detector per type with identical params, e.g. to compare algorithms on the same
+  // audio by passing each to set_latest_samples_on().
+  pub fn create_detectors(
+    &self,
+    detector_types: Vec<String>,
+    window_samples: usize,
+    sample_rate: usize,
+    power_threshold: f32,
+    clarity_threshold: f32,
+  ) -> Vec<pitch_detector::PitchDetector> {
+    detector_types
+      .into_iter()
+      .filter_map(|detector_type| {
+        self.create_pitch_detector(
+          detector_type,
+          window_samples,
+          sample_rate,
+          power_threshold,
+          clarity_threshold,
+        )
+      })
+      .collect()
+  }
+
   pub fn get_time_of_first_sample(&self) -> usize {
     self.time_of_last_added_sample - self.recent_audio_sample_f32s.len()
   }
 
-  pub fn set_latest_samples_on(&self, detector: &mut pitch_detector::PitchDetector) {
-    detector.set_audio_samples(
-      self.get_time_of_first_sample(),
-      self.recent_audio_sample_f32s.asc_iter().cloned().collect(),
-    )
+  pub fn set_latest_samples_on(
+    &self,
+    detector: &mut pitch_detector::PitchDetector,
+  ) -> pitch_detector::PitchesResult {
+    let mut samples = self.latest_samples();
+    if self.normalize {
+      normalize_to_peak(&mut samples, self.target_peak);
+    }
+
+    detector.set_audio_samples(self.get_time_of_first_sample(), samples)
   }
 
-  // pub fn get_latest_samples(&self) -> Vec<f32> {
-  //   self
-  //     .recent_audio_sample_f32s
-  //     .iter()
-  //     .rev()
-  //     .flat_map(|vec| vec.iter())
-  //     .cloned()
-  //     .collect()
-  // }
+  // Like set_latest_samples_on(), but hands over only the stored samples from start up to (not
+  // including) end, both in the same sample times as get_time_of_first_sample(), e.g. to analyze
+  // a region selected in a recording. Reset the detector first to re-analyze a region it has
+  // already processed. Returns a "sample_range_out_of_bounds" error result if the range isn't
+  // within the stored samples.
+  pub fn set_sample_range_on(
+    &self,
+    detector: &mut pitch_detector::PitchDetector,
+    start: usize,
+    end: usize,
+  ) -> pitch_detector::PitchesResult {
+    let time_of_first_sample = self.get_time_of_first_sample();
+    if start < time_of_first_sample || end > self.time_of_last_added_sample || start > end {
+      return pitch_detector::PitchesResult::from_error(
+        pitch_detector::AnalysisError::SampleRangeOutOfBounds,
+        format!(
+          "set_sample_range_on() range {}..{} is outside the stored samples {}..{}",
+          start, end, time_of_first_sample, self.time_of_last_added_sample
+        ),
+      );
+    }
+
+    let mut samples: Vec<f32> = self
+      .recent_audio_sample_f32s
+      .asc_iter()
+      .skip(start - time_of_first_sample)
+      .take(end - start)
+      .cloned()
+      .collect();
+    if self.normalize {
+      normalize_to_peak(&mut samples, self.target_peak);
+    }
+
+    detector.set_audio_samples(start, samples)
+  }
+
+  // The stored samples, oldest first, copied in one go into a typed array (e.g. for drawing the
+  // waveform) rather than element by element.
+  pub fn get_latest_samples(&self) -> js_sys::Float32Array {
+    js_sys::Float32Array::from(&self.latest_samples()[..])
+  }
+
+  // Chronological order matters to every detector, so rely on asc_iter() (oldest first) rather
+  // than iter(), which yields the newest sample first.
+  fn latest_samples(&self) -> Vec<f32> {
+    self.recent_audio_sample_f32s.asc_iter().cloned().collect()
+  }
+}
+
+// Generic methods can't be exported to JavaScript.
+impl AudioSamplesProcessor {
+  // Calls f with each stored sample, oldest first, without copying them as latest_samples()
+  // does. For read-only measurements over the buffer.
+  pub fn for_each_sample<F: FnMut(f32)>(&self, mut f: F) {
+    for sample in self.recent_audio_sample_f32s.asc_iter() {
+      f(*sample);
+    }
+  }
 }
 
 #[cfg(test)]
@@ -89,22 +523,824 @@ impl AudioSamplesProcessor {
 mod tests {
   use super::*;
 
+  use crate::test_utils;
+
   const AUDIO_SAMPLES_PER_CHUNK: usize = 128;
 
+  mod creating_detectors {
+    use super::*;
+
+    #[test]
+    fn each_detector_finds_pitches_in_shared_samples() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      let sine_wave_samples = test_utils::sin_signal(440.0, 4096, 48000);
+      for chunk in sine_wave_samples.chunks(AUDIO_SAMPLES_PER_CHUNK) {
+        processor.add_samples_chunk(chunk.to_vec());
+      }
+
+      let mut detectors = processor.create_detectors(
+        vec![
+          String::from("McLeod"),
+          String::from("YIN"),
+          String::from("Autocorrelation"),
+        ],
+        1024,
+        48000,
+        0.25,
+        0.6,
+      );
+      assert_eq!(detectors.len(), 3);
+
+      for detector in detectors.iter_mut() {
+        processor.set_latest_samples_on(detector);
+
+        let pitches = detector.pitches_vec();
+        assert!(!pitches.is_empty());
+        for pitch in pitches {
+          assert!((pitch.frequency - 440.0).abs() < 5.0);
+        }
+      }
+    }
+  }
+
+  mod sample_ranges {
+    use super::*;
+
+    fn processor_with_tone() -> AudioSamplesProcessor {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk(test_utils::sin_signal(440.0, 8192, 48000));
+      processor
+    }
+
+    #[test]
+    fn pitches_fall_within_range() {
+      let processor = processor_with_tone();
+      let mut detector = processor
+        .create_pitch_detector(String::from("McLeod"), 1024, 48000, 0.25, 0.6)
+        .unwrap();
+
+      processor.set_sample_range_on(&mut detector, 2048, 6144);
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        let window_start = (pitch.t * 48000.0).round() as usize;
+        assert!(window_start >= 2048 && window_start + 1024 <= 6144);
+        assert!((pitch.frequency - 440.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn errors_for_range_outside_stored_samples() {
+      let processor = processor_with_tone();
+      let mut detector = processor
+        .create_pitch_detector(String::from("McLeod"), 1024, 48000, 0.25, 0.6)
+        .unwrap();
+
+      let result = processor.set_sample_range_on(&mut detector, 4096, 9000);
+
+      assert_eq!(result.code(), "sample_range_out_of_bounds");
+      assert_eq!(
+        result.message(),
+        "set_sample_range_on() range 4096..9000 is outside the stored samples 0..8192"
+      );
+    }
+  }
+
+  mod capacity {
+    use super::*;
+
+    fn chunks_until_sufficient(window: usize) -> usize {
+      let mut processor = AudioSamplesProcessor::for_window(window);
+      let detector = processor
+        .create_pitch_detector(String::from("McLeod"), window, 48000, 0.25, 0.6)
+        .unwrap();
+
+      let mut chunks = 0;
+      while !processor.has_sufficient_samples(&detector) {
+        processor.add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
+        chunks += 1;
+      }
+      chunks
+    }
+
+    #[test]
+    fn holds_two_windows() {
+      let processor = AudioSamplesProcessor::for_window(1024);
+
+      assert_eq!(processor.recent_audio_sample_f32s.capacity(), 2048);
+    }
+
+    #[test]
+    fn holds_two_downsampled_windows() {
+      let mut params = pitch_detector::make_params(1024, 48000, 0.25, 0.6);
+      params.set_downsample_factor(4);
+      let mut processor = AudioSamplesProcessor::for_params(params);
+      let mut detector = pitch_detector::PitchDetector::new(String::from("McLeod"), params);
+
+      assert_eq!(processor.recent_audio_sample_f32s.capacity(), 8192);
+
+      for _ in 0..31 {
+        processor.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]);
+      }
+      assert!(!processor.has_sufficient_samples(&detector));
+
+      processor.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]);
+      assert!(processor.has_sufficient_samples(&detector));
+      assert_eq!(
+        processor.set_latest_samples_on(&mut detector).code(),
+        "success"
+      );
+    }
+
+    #[test]
+    #[should_panic(
+      expected = "AudioSamplesProcessor window must span between 1 and 8192 samples, got 16384"
+    )]
+    fn panics_on_oversized_downsampled_window() {
+      let mut params = pitch_detector::make_params(4096, 48000, 0.25, 0.6);
+      params.set_downsample_factor(4);
+
+      AudioSamplesProcessor::for_params(params);
+    }
+
+    #[test]
+    fn smaller_window_is_ready_sooner() {
+      assert_eq!(chunks_until_sufficient(1024), 8);
+      assert_eq!(chunks_until_sufficient(8192), 64);
+    }
+
+    #[test]
+    fn keeps_only_latest_samples() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+
+      for i in 0..32 {
+        processor.add_samples_chunk(vec![i as f32; AUDIO_SAMPLES_PER_CHUNK]);
+      }
+
+      assert_eq!(processor.get_time_of_first_sample(), 4096 - 2048);
+      assert_eq!(processor.fill_fraction(), 1.0);
+    }
+
+    #[test]
+    fn reports_stored_amounts_through_wraparound() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+      assert_eq!(processor.max_chunks(), 16);
+      assert_eq!(processor.stored_chunks(), 0);
+      assert_eq!(processor.stored_samples(), 0);
+
+      for i in 1..=20 {
+        processor.add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
+
+        assert_eq!(processor.stored_chunks(), i.min(16));
+        assert_eq!(
+          processor.stored_samples(),
+          i.min(16) * AUDIO_SAMPLES_PER_CHUNK
+        );
+      }
+
+      assert_eq!(processor.max_chunks(), 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "AudioSamplesProcessor window must be between 1 and 8192, got 16384")]
+    fn panics_on_oversized_window() {
+      AudioSamplesProcessor::for_window(16384);
+    }
+  }
+
+  mod ordering {
+    use super::*;
+
+    // Pushes chunks of consecutive values, so stored samples are in order if and only if they're
+    // strictly increasing.
+    fn processor_after_chunks(num_chunks: usize) -> AudioSamplesProcessor {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+
+      for chunk in 0..num_chunks {
+        let start = chunk * AUDIO_SAMPLES_PER_CHUNK;
+        processor.add_samples_chunk(
+          (start..start + AUDIO_SAMPLES_PER_CHUNK)
+            .map(|i| i as f32)
+            .collect(),
+        );
+      }
+      processor
+    }
+
+    #[test]
+    fn for_each_sample_visits_latest_samples_in_order() {
+      let processor = processor_after_chunks(20);
+
+      let mut visited = vec![];
+      processor.for_each_sample(|sample| visited.push(sample));
+      assert_eq!(visited, processor.latest_samples());
+
+      let mut sum = 0.0;
+      processor.for_each_sample(|sample| sum += sample);
+      assert_eq!(sum, processor.latest_samples().iter().sum::<f32>());
+    }
+
+    #[test]
+    fn latest_samples_are_chronological_before_and_after_wraparound() {
+      // Capacity is 16 chunks, so this covers partly full, exactly full and several wraps.
+      for num_chunks in [1, 15, 16, 17, 31, 50].iter() {
+        let processor = processor_after_chunks(*num_chunks);
+        let samples = processor.latest_samples();
+
+        assert!(
+          samples.windows(2).all(|pair| pair[0] < pair[1]),
+          "samples out of order after {} chunks",
+          num_chunks
+        );
+        assert_eq!(
+          samples[0] as usize,
+          processor.get_time_of_first_sample(),
+          "first sample doesn't match its time after {} chunks",
+          num_chunks
+        );
+        assert_eq!(
+          samples[samples.len() - 1] as usize,
+          num_chunks * AUDIO_SAMPLES_PER_CHUNK - 1
+        );
+      }
+    }
+  }
+
+  mod clearing {
+    use super::*;
+
+    #[test]
+    fn discards_samples() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+      let detector = processor
+        .create_pitch_detector(String::from("McLeod"), 1024, 48000, 0.25, 0.6)
+        .unwrap();
+
+      processor.add_samples_chunk(vec![0.5; 2048]);
+      assert!(processor.has_sufficient_samples(&detector));
+
+      processor.clear();
+
+      assert!(!processor.has_sufficient_samples(&detector));
+      assert_eq!(processor.get_time_of_first_sample(), 0);
+      assert_eq!(processor.fill_fraction(), 0.0);
+      assert_eq!(processor.envelope(), 0.0);
+    }
+
+    #[test]
+    fn restarts_sample_times() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+
+      processor.add_samples_chunk(vec![0.5; 4096]);
+      processor.clear();
+      processor.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]);
+
+      assert_eq!(processor.get_time_of_first_sample(), 0);
+      assert_eq!(
+        processor.latest_samples(),
+        vec![0.5; AUDIO_SAMPLES_PER_CHUNK]
+      );
+    }
+  }
+
+  mod fill_fraction {
+    use super::*;
+
+    #[test]
+    fn is_zero_when_empty() {
+      assert_eq!(AudioSamplesProcessor::new().fill_fraction(), 0.0);
+    }
+
+    #[test]
+    fn is_half_at_half_capacity() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      processor.add_samples_chunk(vec![0.0; CAPACITY / 2]);
+
+      assert!((processor.fill_fraction() - 0.5).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn stays_at_one_once_full() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      processor.add_samples_chunk(vec![0.0; CAPACITY]);
+      assert_eq!(processor.fill_fraction(), 1.0);
+
+      processor.add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
+      assert_eq!(processor.fill_fraction(), 1.0);
+    }
+  }
+
+  mod envelope {
+    use super::*;
+
+    #[test]
+    fn follower_moves_faster_when_rising() {
+      let mut follower = EnvelopeFollower::new(0.5, 0.1);
+
+      assert_eq!(follower.update(1.0), 0.5);
+      assert_eq!(follower.update(1.0), 0.75);
+      assert!((follower.update(0.0) - 0.675).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rises_quickly_then_decays_gradually() {
+      let mut processor = AudioSamplesProcessor::new();
+      let sine = test_utils::sin_signal(440.0, 1280, 48000);
+      let level = std::f32::consts::FRAC_1_SQRT_2;
+
+      assert_eq!(processor.envelope(), 0.0);
+
+      for chunk in sine.chunks(AUDIO_SAMPLES_PER_CHUNK) {
+        processor.add_samples_chunk(chunk.to_vec());
+      }
+      let peak_envelope = processor.envelope();
+      assert!(peak_envelope > level * 0.95);
+
+      let mut previous = peak_envelope;
+      for _ in 0..5 {
+        processor.add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
+        assert!(processor.envelope() < previous);
+        previous = processor.envelope();
+      }
+      // Still well above silence after several silent chunks.
+      assert!(previous > peak_envelope * 0.5);
+    }
+
+    #[test]
+    fn updates_per_chunk_when_several_are_added_at_once() {
+      let mut at_once = AudioSamplesProcessor::new();
+      at_once.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK * 3]);
+
+      let mut one_by_one = AudioSamplesProcessor::new();
+      for _ in 0..3 {
+        one_by_one.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]);
+      }
+
+      assert_eq!(at_once.envelope(), one_by_one.envelope());
+    }
+
+    #[test]
+    fn coefficients_are_configurable() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.set_envelope_coefficients(1.0, 0.1);
+
+      processor.add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]);
+
+      assert_eq!(processor.envelope(), 0.5);
+    }
+  }
+
+  mod clipping {
+    use super::*;
+
+    fn processor_with(samples: Vec<f32>) -> AudioSamplesProcessor {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk(samples);
+      processor
+    }
+
+    #[test]
+    fn peak_of_empty_buffer_is_zero() {
+      assert_eq!(AudioSamplesProcessor::new().peak_amplitude(), 0.0);
+    }
+
+    #[test]
+    fn peak_is_largest_magnitude() {
+      let mut samples = vec![0.1; 128];
+      samples[5] = -0.7;
+      samples[9] = 0.4;
+
+      assert_eq!(processor_with(samples).peak_amplitude(), 0.7);
+    }
+
+    #[test]
+    fn moderate_sine_is_not_clipping() {
+      let samples = test_utils::sin_signal(440.0, 1024, 48000)
+        .iter()
+        .map(|sample| sample * 0.8)
+        .collect();
+
+      assert!(!processor_with(samples).is_clipping(0.99));
+    }
+
+    #[test]
+    fn saturated_signal_is_clipping() {
+      let samples = test_utils::sin_signal(440.0, 1024, 48000)
+        .iter()
+        .map(|sample| (sample * 2.0).clamp(-1.0, 1.0))
+        .collect();
+
+      assert!(processor_with(samples).is_clipping(0.99));
+    }
+  }
+
+  mod zero_crossing_rate {
+    use super::*;
+
+    fn processor_with(samples: Vec<f32>) -> AudioSamplesProcessor {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk(samples);
+      processor
+    }
+
+    #[test]
+    fn sine_crosses_twice_per_period() {
+      let processor = processor_with(test_utils::sin_signal(440.0, 4096, 48000));
+
+      let rate = processor.zero_crossing_rate(2048);
+
+      assert!((rate - 2.0 * 440.0 / 48000.0).abs() < 0.002);
+    }
+
+    #[test]
+    fn white_noise_crosses_often() {
+      let processor = processor_with(test_utils::white_noise(4096, 3));
+
+      assert!(processor.zero_crossing_rate(2048) > 0.4);
+    }
+
+    #[test]
+    fn zero_without_enough_samples() {
+      assert_eq!(AudioSamplesProcessor::new().zero_crossing_rate(2048), 0.0);
+    }
+
+    #[test]
+    fn only_considers_latest_window() {
+      let mut samples = test_utils::white_noise(2048, 3);
+      samples.extend(test_utils::sin_signal(440.0, 2048, 48000));
+      let processor = processor_with(samples);
+
+      assert!(processor.zero_crossing_rate(2048) < 0.05);
+    }
+  }
+
+  mod magnitude_spectrum {
+    use super::*;
+
+    #[test]
+    fn peaks_at_bin_nearest_tone() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk(test_utils::sin_signal(440.0, 4096, 48000));
+
+      let spectrum = processor.magnitude_spectrum(2048);
+      let peak = (0..spectrum.len())
+        .max_by(|a, b| spectrum[*a].partial_cmp(&spectrum[*b]).unwrap())
+        .unwrap();
+
+      assert_eq!(spectrum.len(), 1024);
+      assert_eq!(peak, (440.0_f32 * 2048.0 / 48000.0).round() as usize);
+    }
+
+    #[test]
+    fn empty_until_window_of_samples() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk(vec![0.5; 1024]);
+
+      assert!(processor.magnitude_spectrum(2048).is_empty());
+    }
+  }
+
+  mod high_pass_filter {
+    use super::*;
+
+    fn add_offset_sine(processor: &mut AudioSamplesProcessor) {
+      let offset_samples: Vec<f32> = test_utils::sin_signal(220.0, CAPACITY, 48000)
+        .iter()
+        .map(|sample| sample + 0.5)
+        .collect();
+      for chunk in offset_samples.chunks(AUDIO_SAMPLES_PER_CHUNK) {
+        processor.add_samples_chunk(chunk.to_vec());
+      }
+    }
+
+    fn mean_of_stored_samples(processor: &AudioSamplesProcessor) -> f32 {
+      processor.recent_audio_sample_f32s.iter().sum::<f32>()
+        / processor.recent_audio_sample_f32s.len() as f32
+    }
+
+    #[test]
+    fn off_by_default() {
+      let mut processor = AudioSamplesProcessor::new();
+      add_offset_sine(&mut processor);
+
+      assert!((mean_of_stored_samples(&processor) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn removes_dc_offset() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.enable_high_pass_filter(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      add_offset_sine(&mut processor);
+
+      assert!(mean_of_stored_samples(&processor).abs() < 0.01);
+    }
+
+    #[test]
+    fn filtered_signal_is_still_detected() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.enable_high_pass_filter(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      add_offset_sine(&mut processor);
+
+      let mut detector = processor
+        .create_pitch_detector(String::from("McLeod"), 2048, 48000, 0.25, 0.6)
+        .unwrap();
+      processor.set_latest_samples_on(&mut detector);
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert!((pitch.frequency - 220.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn is_continuous_across_chunks() {
+      let samples = test_utils::sin_signal(220.0, 1024, 48000);
+
+      let mut whole = HighPassFilter::new(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      let expected: Vec<f32> = samples
+        .iter()
+        .map(|sample| whole.process(*sample))
+        .collect();
+
+      let mut processor = AudioSamplesProcessor::new();
+      processor.enable_high_pass_filter(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      for chunk in samples.chunks(AUDIO_SAMPLES_PER_CHUNK) {
+        processor.add_samples_chunk(chunk.to_vec());
+      }
+      let stored: Vec<f32> = processor
+        .recent_audio_sample_f32s
+        .asc_iter()
+        .cloned()
+        .collect();
+
+      assert_eq!(stored, expected);
+    }
+
+    #[test]
+    fn filters_silence_filling_a_gap() {
+      let chunk: Vec<f32> = vec![0.5; AUDIO_SAMPLES_PER_CHUNK];
+
+      let mut whole = HighPassFilter::new(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      let expected: Vec<f32> = chunk
+        .iter()
+        .chain(vec![0.0; AUDIO_SAMPLES_PER_CHUNK].iter())
+        .chain(chunk.iter())
+        .map(|sample| whole.process(*sample))
+        .collect();
+
+      let mut processor = AudioSamplesProcessor::new();
+      processor.enable_high_pass_filter(DEFAULT_HIGH_PASS_CUTOFF_HZ, 48000);
+      processor.add_samples_chunk_at(chunk.clone(), 0);
+      processor.add_samples_chunk_at(chunk, 256);
+
+      assert_eq!(processor.latest_samples(), expected);
+    }
+  }
+
+  mod normalizing {
+    use super::*;
+
+    fn pitches_of_quiet_sine(normalize: bool) -> Vec<pitch_detector::Pitch> {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.normalize = normalize;
+
+      let quiet_samples: Vec<f32> = test_utils::sin_signal(440.0, 4096, 48000)
+        .iter()
+        .map(|sample| sample * 0.01)
+        .collect();
+      for chunk in quiet_samples.chunks(AUDIO_SAMPLES_PER_CHUNK) {
+        processor.add_samples_chunk(chunk.to_vec());
+      }
+
+      let mut detector = processor
+        .create_pitch_detector(String::from("McLeod"), 1024, 48000, 0.25, 0.6)
+        .unwrap();
+      processor.set_latest_samples_on(&mut detector);
+      detector.pitches_vec()
+    }
+
+    #[test]
+    fn scales_peak_to_target() {
+      let mut samples = vec![0.1, -0.25, 0.05];
+
+      normalize_to_peak(&mut samples, 0.5);
+
+      assert_eq!(samples, vec![0.2, -0.5, 0.1]);
+    }
+
+    #[test]
+    fn leaves_silence_untouched() {
+      let mut samples = vec![0.0; 4];
+
+      normalize_to_peak(&mut samples, 1.0);
+
+      assert_eq!(samples, vec![0.0; 4]);
+    }
+
+    #[test]
+    fn quiet_signal_is_below_power_threshold_by_default() {
+      assert!(pitches_of_quiet_sine(false).is_empty());
+    }
+
+    #[test]
+    fn detects_quiet_signal_once_normalized() {
+      let pitches = pitches_of_quiet_sine(true);
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert!((pitch.frequency - 440.0).abs() < 5.0);
+      }
+    }
+  }
+
   mod adding_samples {
     use super::*;
 
     #[test]
-    #[should_panic(expected = "add_samples_chunk() requires 128 samples, instead got 0")]
+    #[should_panic(
+      expected = "add_samples_chunk() requires a multiple of 128 samples, instead got 0"
+    )]
     fn panics_on_empty_samples_vec() {
       AudioSamplesProcessor::new().add_samples_chunk(vec![]);
     }
 
+    #[test]
+    fn try_add_rejects_partial_chunk() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      assert!(!processor.try_add_samples_chunk(vec![0.5; 64]));
+      assert_eq!(processor.chunk_size, 128);
+      assert!(processor.latest_samples().is_empty());
+      assert_eq!(processor.get_time_of_first_sample(), 0);
+
+      assert!(processor.try_add_samples_chunk(vec![0.5; AUDIO_SAMPLES_PER_CHUNK]));
+      assert_eq!(processor.latest_samples().len(), AUDIO_SAMPLES_PER_CHUNK);
+    }
+
     #[test]
     fn adds_samples_if_of_correct_count() {
       AudioSamplesProcessor::new().add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
     }
 
+    #[test]
+    #[should_panic(
+      expected = "add_samples_chunk() requires a multiple of 128 samples, instead got 200"
+    )]
+    fn panics_on_partial_chunk() {
+      AudioSamplesProcessor::new().add_samples_chunk(vec![0.0; 200]);
+    }
+
+    #[test]
+    fn accepts_multiple_chunks_at_once() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      processor.add_samples_chunk(vec![0.0; AUDIO_SAMPLES_PER_CHUNK * 2]);
+
+      assert_eq!(processor.recent_audio_sample_f32s.len(), 256);
+    }
+
+    #[test]
+    fn fills_dropped_chunk_with_silence() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+
+      processor.add_samples_chunk_at(vec![1.0; AUDIO_SAMPLES_PER_CHUNK], 0);
+      // The chunk at 128 was dropped.
+      processor.add_samples_chunk_at(vec![2.0; AUDIO_SAMPLES_PER_CHUNK], 256);
+
+      let mut expected = vec![1.0; AUDIO_SAMPLES_PER_CHUNK];
+      expected.extend(vec![0.0; AUDIO_SAMPLES_PER_CHUNK]);
+      expected.extend(vec![2.0; AUDIO_SAMPLES_PER_CHUNK]);
+      assert_eq!(processor.latest_samples(), expected);
+      assert_eq!(processor.get_time_of_first_sample(), 0);
+    }
+
+    #[test]
+    fn keeps_timing_after_long_gap() {
+      let mut processor = AudioSamplesProcessor::for_window(1024);
+
+      processor.add_samples_chunk_at(vec![1.0; AUDIO_SAMPLES_PER_CHUNK], 0);
+      processor.add_samples_chunk_at(vec![2.0; AUDIO_SAMPLES_PER_CHUNK], 48000);
+
+      // Only the latest 2048 samples are kept, ending with the chunk at 48000.
+      assert_eq!(processor.get_time_of_first_sample(), 48128 - 2048);
+      assert_eq!(processor.latest_samples()[2047], 2.0);
+      assert_eq!(processor.latest_samples()[1919], 0.0);
+    }
+
+    #[test]
+    #[should_panic(
+      expected = "add_samples_chunk_at() requires a time of at least 128, instead got 64"
+    )]
+    fn panics_on_overlapping_chunk() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      processor.add_samples_chunk_at(vec![0.0; AUDIO_SAMPLES_PER_CHUNK], 0);
+      processor.add_samples_chunk_at(vec![0.0; AUDIO_SAMPLES_PER_CHUNK], 64);
+    }
+
+    #[test]
+    #[should_panic(
+      expected = "add_samples_chunk_at() requires a multiple of 128 samples, instead got 64"
+    )]
+    fn panics_on_partial_chunk_at_time() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      processor.add_samples_chunk_at(vec![0.0; AUDIO_SAMPLES_PER_CHUNK], 0);
+      processor.add_samples_chunk_at(vec![0.0; 64], 256);
+    }
+
+    #[test]
+    fn partial_chunk_at_time_adds_nothing() {
+      let mut processor = AudioSamplesProcessor::new();
+      processor.add_samples_chunk_at(vec![1.0; AUDIO_SAMPLES_PER_CHUNK], 0);
+
+      let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        processor.add_samples_chunk_at(vec![2.0; 64], 256)
+      }));
+
+      assert!(result.is_err());
+      assert_eq!(
+        processor.latest_samples(),
+        vec![1.0; AUDIO_SAMPLES_PER_CHUNK]
+      );
+      processor.add_samples_chunk_at(vec![2.0; AUDIO_SAMPLES_PER_CHUNK], 128);
+    }
+
+    #[test]
+    fn mixes_stereo_chunks_to_mono() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      let left: Vec<f32> = (0..AUDIO_SAMPLES_PER_CHUNK).map(|i| i as f32).collect();
+      let right: Vec<f32> = (0..AUDIO_SAMPLES_PER_CHUNK)
+        .map(|i| -0.5 * i as f32)
+        .collect();
+      processor.add_stereo_chunk(left, right);
+
+      let expected: Vec<f32> = (0..AUDIO_SAMPLES_PER_CHUNK)
+        .map(|i| 0.25 * i as f32)
+        .collect();
+      assert_eq!(processor.latest_samples(), expected);
+    }
+
+    #[test]
+    fn normalizes_i16_chunks() {
+      let mut processor = AudioSamplesProcessor::new();
+
+      let mut samples = vec![0i16; AUDIO_SAMPLES_PER_CHUNK];
+      samples[0] = i16::MIN;
+      samples[1] = i16::MAX;
+      samples[2] = 16384;
+      samples[3] = -1;
+      processor.add_samples_chunk_i16(samples);
+
+      let stored = processor.latest_samples();
+      assert_eq!(stored[0..4], [-1.0, 32767.0 / 32768.0, 0.5, -1.0 / 32768.0]);
+      assert!(stored.iter().all(|sample| (-1.0..1.0).contains(sample)));
+    }
+
+    #[test]
+    #[should_panic(
+      expected = "add_stereo_chunk() requires channels of equal length, instead got 128 and 256"
+    )]
+    fn panics_on_mismatched_stereo_channels() {
+      AudioSamplesProcessor::new().add_stereo_chunk(
+        vec![0.0; AUDIO_SAMPLES_PER_CHUNK],
+        vec![0.0; AUDIO_SAMPLES_PER_CHUNK * 2],
+      );
+    }
+
+    #[test]
+    #[should_panic(expected = "AudioSamplesProcessor chunk size must be greater than 0")]
+    fn panics_on_zero_chunk_size() {
+      AudioSamplesProcessor::with_chunk_size(0);
+    }
+
+    #[test]
+    fn keeps_order_and_timing_with_larger_chunks() {
+      let mut processor = AudioSamplesProcessor::with_chunk_size(256);
+
+      // Enough chunks of increasing values to wrap around the buffer once.
+      let num_chunks = CAPACITY / 256 + 1;
+      for i in 0..num_chunks {
+        processor.add_samples_chunk(vec![i as f32; 256]);
+      }
+
+      assert_eq!(processor.get_time_of_first_sample(), 256);
+
+      let stored: Vec<f32> = processor
+        .recent_audio_sample_f32s
+        .asc_iter()
+        .cloned()
+        .collect();
+      let expected: Vec<f32> = (1..num_chunks).flat_map(|i| vec![i as f32; 256]).collect();
+      assert_eq!(stored, expected);
+    }
+
     //   #[test]
     //   fn returns_added_chunks_in_correct_order() {
     //     let mut processor = AudioSamplesProcessor::new();
@@ -0,0 +1,198 @@
+use pitch_detection::{Pitch, PitchDetector, PitchDetectorHistory};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+// Half-width of a Hann window's main lobe in bins of the unpadded transform. Energy within this
+// distance of the peak is counted as belonging to it.
+const MAIN_LOBE_HALF_WIDTH: f32 = 2.0;
+
+// Frequency-domain pitch detector taking the strongest spectral peak as the pitch. Unlike the
+// time-domain detectors this reports the loudest partial, so tones whose overtones are stronger
+// than the fundamental are reported at the overtone.
+pub struct FftDetector {
+  size: usize,
+  fft: Arc<dyn Fft<f32>>,
+  // Hann window applied before transforming to reduce leakage between bins.
+  window: Vec<f32>,
+  // Working memory for the windowed, zero padded signal and then its spectrum.
+  spectrum: Vec<Complex<f32>>,
+}
+
+impl FftDetector {
+  pub fn new(size: usize, padding: usize) -> FftDetector {
+    let fft_size = size + padding;
+    let dx = 2.0 * std::f32::consts::PI / (size.max(2) - 1) as f32;
+
+    FftDetector {
+      size,
+      fft: FftPlanner::new().plan_fft_forward(fft_size),
+      window: (0..size)
+        .map(|i| 0.5 - 0.5 * (i as f32 * dx).cos())
+        .collect(),
+      spectrum: vec![Complex::new(0.0, 0.0); fft_size],
+    }
+  }
+
+  // Energy of each bin up to the Nyquist frequency.
+  fn energies(&mut self, signal: &[f32]) -> Vec<f32> {
+    for (i, bin) in self.spectrum.iter_mut().enumerate() {
+      *bin = match i < self.size {
+        true => Complex::new(signal[i] * self.window[i], 0.0),
+        false => Complex::new(0.0, 0.0),
+      };
+    }
+
+    self.fft.process(&mut self.spectrum);
+
+    self.spectrum[0..self.spectrum.len() / 2]
+      .iter()
+      .map(|bin| bin.norm_sqr())
+      .collect()
+  }
+
+  // Magnitude of each bin up to the Nyquist frequency, bin i being at i * sample_rate / (size +
+  // padding) Hz, e.g. for drawing a spectrum. The signal must have at least size samples.
+  pub fn magnitude_spectrum(&mut self, signal: &[f32]) -> Vec<f32> {
+    self
+      .energies(signal)
+      .iter()
+      .map(|energy| energy.sqrt())
+      .collect()
+  }
+}
+
+// Refines the peak to a fractional bin by fitting a parabola through the neighbouring
+// magnitudes.
+fn interpolate(energies: &[f32], bin: usize) -> f32 {
+  if bin < 1 || bin + 1 >= energies.len() {
+    return bin as f32;
+  }
+
+  let (prev, curr, next) = (
+    energies[bin - 1].sqrt(),
+    energies[bin].sqrt(),
+    energies[bin + 1].sqrt(),
+  );
+  let denominator = prev - 2.0 * curr + next;
+
+  match denominator.abs() > f32::EPSILON {
+    true => bin as f32 + 0.5 * (prev - next) / denominator,
+    false => bin as f32,
+  }
+}
+
+impl PitchDetector<f32> for FftDetector {
+  fn get_pitch(
+    &mut self,
+    signal: &[f32],
+    sample_rate: usize,
+    power_threshold: f32,
+    clarity_threshold: f32,
+    _history: Option<PitchDetectorHistory>,
+  ) -> Option<Pitch<f32>> {
+    if signal.len() < self.size {
+      return None;
+    }
+
+    let power: f32 = signal[..self.size].iter().map(|s| s * s).sum();
+    if power < power_threshold {
+      return None;
+    }
+
+    let energies = self.energies(signal);
+
+    // Skip the DC bin.
+    let peak =
+      (1..energies.len()).max_by(|a, b| energies[*a].partial_cmp(&energies[*b]).unwrap())?;
+
+    // Clarity is the share of the total energy in the peak's main lobe: near 1 for a pure tone,
+    // falling as noise or other partials take a larger share.
+    let total_energy: f32 = energies[1..].iter().sum();
+    // A silent window passes a zero power threshold but has no peak to measure.
+    if total_energy <= 0.0 {
+      return None;
+    }
+
+    let lobe =
+      (MAIN_LOBE_HALF_WIDTH * self.spectrum.len() as f32 / self.size as f32).ceil() as usize;
+    let lobe_start = peak.saturating_sub(lobe).max(1);
+    let lobe_end = (peak + lobe + 1).min(energies.len());
+    let lobe_energy: f32 = energies[lobe_start..lobe_end].iter().sum();
+
+    let clarity = lobe_energy / total_energy;
+    if clarity < clarity_threshold {
+      return None;
+    }
+
+    Some(Pitch {
+      frequency: interpolate(&energies, peak) * sample_rate as f32 / self.spectrum.len() as f32,
+      clarity,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils;
+
+  const SAMPLE_RATE: usize = 48000;
+  const WINDOW: usize = 2048;
+
+  fn detect(signal: &[f32]) -> Option<Pitch<f32>> {
+    FftDetector::new(WINDOW, WINDOW / 2).get_pitch(signal, SAMPLE_RATE, 0.25, 0.0, None)
+  }
+
+  #[test]
+  fn detects_440_hz() {
+    let pitch = detect(&test_utils::sin_signal(440.0, WINDOW, SAMPLE_RATE)).unwrap();
+
+    assert!((pitch.frequency - 440.0).abs() < 2.0);
+    assert!(pitch.clarity > 0.9);
+  }
+
+  #[test]
+  fn clarity_falls_as_noise_increases() {
+    let sine = test_utils::sin_signal(440.0, WINDOW, SAMPLE_RATE);
+    let noise = test_utils::white_noise(WINDOW, 5);
+
+    let clarities: Vec<f32> = [0.0, 0.5, 1.0]
+      .iter()
+      .map(|noise_level| {
+        let signal: Vec<f32> = sine
+          .iter()
+          .zip(noise.iter())
+          .map(|(s, n)| s + noise_level * n)
+          .collect();
+        detect(&signal).unwrap().clarity
+      })
+      .collect();
+
+    assert!(clarities[0] > clarities[1]);
+    assert!(clarities[1] > clarities[2]);
+  }
+
+  #[test]
+  fn no_pitch_for_silence() {
+    assert!(detect(&test_utils::new_real_buffer(WINDOW)).is_none());
+  }
+
+  #[test]
+  fn no_pitch_for_silence_without_power_threshold() {
+    let pitch = FftDetector::new(WINDOW, WINDOW / 2).get_pitch(
+      &test_utils::new_real_buffer(WINDOW),
+      SAMPLE_RATE,
+      0.0,
+      0.0,
+      None,
+    );
+
+    assert!(pitch.is_none());
+  }
+
+  #[test]
+  fn no_pitch_for_short_signal() {
+    assert!(detect(&test_utils::sin_signal(440.0, WINDOW / 2, SAMPLE_RATE)).is_none());
+  }
+}
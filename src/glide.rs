@@ -0,0 +1,150 @@
+// Glides (portamento) found in a sequence of detected pitches.
+
+use super::pitch_detector::Pitch;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+// Smallest change between consecutive pitches counted as movement rather than jitter.
+const MIN_STEP_CENTS: f32 = 5.0;
+
+// A glide must keep moving for at least this many consecutive steps...
+const MIN_GLIDE_STEPS: usize = 3;
+
+// ...and cover at least this distance, so a jump between discrete notes isn't a glide.
+const MIN_GLIDE_CENTS: f32 = 50.0;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Glide {
+  pub start_t: f32,
+  pub end_t: f32,
+  pub start_frequency: f32,
+  pub end_frequency: f32,
+  // Signed distance travelled, positive for upward glides.
+  pub cents: f32,
+}
+
+fn cents_between(from: f32, to: f32) -> f32 {
+  1200.0 * (to / from).log2()
+}
+
+fn glide_between(start: &Pitch, end: &Pitch) -> Glide {
+  Glide {
+    start_t: start.t,
+    end_t: end.t,
+    start_frequency: start.frequency,
+    end_frequency: end.frequency,
+    cents: cents_between(start.frequency, end.frequency),
+  }
+}
+
+// Runs of pitches (in time order) that move steadily in one direction, each step by more than
+// MIN_STEP_CENTS, for long enough to distinguish a glide from a change of note.
+pub fn analyze_glide(pitches: &[Pitch]) -> Vec<Glide> {
+  let mut glides = vec![];
+
+  let is_glide = |start: usize, end: usize| {
+    end - start >= MIN_GLIDE_STEPS
+      && cents_between(pitches[start].frequency, pitches[end].frequency).abs() >= MIN_GLIDE_CENTS
+  };
+
+  // Index of the first pitch of the current run, and the run's direction (1 up, -1 down).
+  let mut run_start = 0;
+  let mut direction = 0.0;
+
+  for i in 1..pitches.len() {
+    let step = cents_between(pitches[i - 1].frequency, pitches[i].frequency);
+    let step_direction = match step.abs() > MIN_STEP_CENTS {
+      true => step.signum(),
+      false => 0.0,
+    };
+
+    if step_direction == 0.0 || step_direction != direction {
+      if direction != 0.0 && is_glide(run_start, i - 1) {
+        glides.push(glide_between(&pitches[run_start], &pitches[i - 1]));
+      }
+
+      run_start = i - 1;
+      direction = step_direction;
+    }
+  }
+
+  if direction != 0.0 && is_glide(run_start, pitches.len() - 1) {
+    glides.push(glide_between(
+      &pitches[run_start],
+      &pitches[pitches.len() - 1],
+    ));
+  }
+
+  glides
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pitches_from(frequencies: &[f32]) -> Vec<Pitch> {
+    frequencies
+      .iter()
+      .enumerate()
+      .map(|(i, frequency)| Pitch {
+        t: i as f32 * 0.01,
+        frequency: *frequency,
+        ..Default::default()
+      })
+      .collect()
+  }
+
+  fn sweep(from: f32, to: f32, steps: usize) -> Vec<f32> {
+    (0..=steps)
+      .map(|i| from + (to - from) * i as f32 / steps as f32)
+      .collect()
+  }
+
+  #[test]
+  fn finds_single_glide_over_sweep() {
+    let glides = analyze_glide(&pitches_from(&sweep(220.0, 440.0, 50)));
+
+    assert_eq!(glides.len(), 1);
+    assert_eq!(glides[0].start_t, 0.0);
+    assert_eq!(glides[0].end_t, 0.5);
+    assert_eq!(glides[0].start_frequency, 220.0);
+    assert_eq!(glides[0].end_frequency, 440.0);
+    assert!((glides[0].cents - 1200.0).abs() < 0.01);
+  }
+
+  #[test]
+  fn downward_glide_has_negative_cents() {
+    let glides = analyze_glide(&pitches_from(&sweep(440.0, 330.0, 20)));
+
+    assert_eq!(glides.len(), 1);
+    assert!(glides[0].cents < -400.0);
+  }
+
+  #[test]
+  fn glide_between_held_notes() {
+    let mut frequencies = vec![220.0; 10];
+    frequencies.extend(sweep(220.0, 330.0, 10));
+    frequencies.extend(vec![330.0; 10]);
+
+    let glides = analyze_glide(&pitches_from(&frequencies));
+
+    assert_eq!(glides.len(), 1);
+    assert_eq!(glides[0].start_frequency, 220.0);
+    assert_eq!(glides[0].end_frequency, 330.0);
+  }
+
+  #[test]
+  fn no_glide_for_change_of_note() {
+    let mut frequencies = vec![220.0; 10];
+    frequencies.extend(vec![330.0; 10]);
+
+    assert!(analyze_glide(&pitches_from(&frequencies)).is_empty());
+  }
+
+  #[test]
+  fn no_glide_for_steady_pitch() {
+    assert!(analyze_glide(&pitches_from(&[440.0; 20])).is_empty());
+    assert!(analyze_glide(&[]).is_empty());
+  }
+}
@@ -0,0 +1,95 @@
+// Musical key estimated from how often each pitch class occurs.
+
+// Krumhansl-Kessler key profiles: how well each pitch class, counted up from the tonic, fits a
+// major or minor key.
+const MAJOR_PROFILE: [f32; 12] = [
+  6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+  6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+// Pearson correlation of the histogram with the profile transposed to start at tonic.
+fn correlation(histogram: &[f32; 12], profile: &[f32; 12], tonic: usize) -> f32 {
+  let mean_histogram = histogram.iter().sum::<f32>() / 12.0;
+  let mean_profile = profile.iter().sum::<f32>() / 12.0;
+
+  let (mut covariance, mut histogram_variance, mut profile_variance) = (0.0, 0.0, 0.0);
+  for (class, count) in histogram.iter().enumerate() {
+    let h = count - mean_histogram;
+    let p = profile[(class + 12 - tonic) % 12] - mean_profile;
+
+    covariance += h * p;
+    histogram_variance += h * h;
+    profile_variance += p * p;
+  }
+
+  match histogram_variance > 0.0 {
+    true => covariance / (histogram_variance * profile_variance).sqrt(),
+    false => 0.0,
+  }
+}
+
+// The tonic pitch class (0 for C up to 11 for B) and whether the key is major (true) or minor
+// (false) that best fits a pitch class histogram, e.g. from
+// PitchesResult::pitch_class_histogram().
+pub fn estimate_key(histogram: &[f32; 12]) -> (usize, bool) {
+  let mut best = (0, true);
+  let mut best_correlation = f32::MIN;
+
+  for tonic in 0..12 {
+    for (profile, major) in [(&MAJOR_PROFILE, true), (&MINOR_PROFILE, false)].iter() {
+      let r = correlation(histogram, profile, tonic);
+      if r > best_correlation {
+        best = (tonic, *major);
+        best_correlation = r;
+      }
+    }
+  }
+
+  best
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Counts for a melody in the scale starting at tonic, with the tonic triad weighted most.
+  fn histogram_for(tonic: usize, scale: &[usize], triad: &[usize]) -> [f32; 12] {
+    let mut histogram = [0.0; 12];
+    for degree in scale {
+      histogram[(tonic + degree) % 12] += 1.0;
+    }
+    for degree in triad {
+      histogram[(tonic + degree) % 12] += 2.0;
+    }
+    histogram
+  }
+
+  const MAJOR_SCALE: [usize; 7] = [0, 2, 4, 5, 7, 9, 11];
+  const MINOR_SCALE: [usize; 7] = [0, 2, 3, 5, 7, 8, 10];
+
+  #[test]
+  fn c_major() {
+    assert_eq!(
+      estimate_key(&histogram_for(0, &MAJOR_SCALE, &[0, 4, 7])),
+      (0, true)
+    );
+  }
+
+  #[test]
+  fn g_major() {
+    assert_eq!(
+      estimate_key(&histogram_for(7, &MAJOR_SCALE, &[0, 4, 7])),
+      (7, true)
+    );
+  }
+
+  #[test]
+  fn a_minor() {
+    assert_eq!(
+      estimate_key(&histogram_for(9, &MINOR_SCALE, &[0, 3, 7])),
+      (9, false)
+    );
+  }
+}
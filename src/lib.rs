@@ -1,10 +1,20 @@
 #[macro_use]
 pub mod macros;
 pub mod audio_samples_processor;
+pub mod fft_detector;
+pub mod glide;
+pub mod key;
+pub mod notes;
 pub mod pitch_detector;
+pub mod resynthesis;
+pub mod segmentation;
+pub mod spectral_flux;
+pub mod tempo;
 pub mod test_utils;
 pub mod timeline;
 mod utils;
+pub mod vibrato;
+pub mod yin_detector;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
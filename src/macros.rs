@@ -1,6 +1,17 @@
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
+//
+// Outside of wasm (e.g. native tests) there is no console to call into, so this falls back to
+// `println!`.
+#[cfg(target_arch = "wasm32")]
 macro_rules! console_log {
   ( $( $t:tt )* ) => {
       web_sys::console::log_1(&format!( $( $t )* ).into());
   }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! console_log {
+  ( $( $t:tt )* ) => {
+      println!( $( $t )* );
+  }
+}
@@ -0,0 +1,322 @@
+// Conversions between frequencies and equal-tempered musical notes.
+
+pub const DEFAULT_REFERENCE_A4: f32 = 440.0;
+
+const A4_MIDI_NOTE: f32 = 69.0;
+
+const NOTE_NAMES: [&str; 12] = [
+  "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+// Chromatic movable-do syllables for each number of semitones above the tonic, using the raised
+// forms for the notes between degrees.
+const SOLFEGE_SYLLABLES: [&str; 12] = [
+  "do", "di", "re", "ri", "mi", "fa", "fi", "sol", "si", "la", "li", "ti",
+];
+
+// Fractional MIDI note number for the frequency, e.g. 69.0 for A4 and 69.5 for a quarter tone
+// above.
+pub fn midi_from_frequency(frequency: f32, reference_a4: f32) -> f32 {
+  A4_MIDI_NOTE + 12.0 * (frequency / reference_a4).log2()
+}
+
+// Frequency of a (possibly fractional) MIDI note number. The inverse of midi_from_frequency().
+pub fn frequency_from_midi(midi: f32, reference_a4: f32) -> f32 {
+  reference_a4 * 2.0_f32.powf((midi - A4_MIDI_NOTE) / 12.0)
+}
+
+// Nearest MIDI note number, clamped to the valid 0..=127 range.
+pub fn nearest_midi_note(frequency: f32, reference_a4: f32) -> u8 {
+  midi_from_frequency(frequency, reference_a4)
+    .round()
+    .clamp(0.0, 127.0) as u8
+}
+
+// Pitch class (0 for C up to 11 for B) of the nearest equal-tempered note, regardless of octave.
+pub fn pitch_class(frequency: f32, reference_a4: f32) -> usize {
+  (midi_from_frequency(frequency, reference_a4).round() as i32).rem_euclid(12) as usize
+}
+
+// Signed distance in cents (-50..=50) from the nearest equal-tempered note.
+pub fn cents_from_nearest_note(frequency: f32, reference_a4: f32) -> f32 {
+  let midi = midi_from_frequency(frequency, reference_a4);
+
+  (midi - midi.round()) * 100.0
+}
+
+fn name_of(midi: i32) -> String {
+  format!(
+    "{}{}",
+    NOTE_NAMES[midi.rem_euclid(12) as usize],
+    midi.div_euclid(12) - 1
+  )
+}
+
+// Name of the nearest equal-tempered note including its octave, e.g. "A4" or "C#5". Returns an
+// empty string for non-positive frequencies.
+pub fn note_name(frequency: f32, reference_a4: f32) -> String {
+  if frequency <= 0.0 {
+    return String::new();
+  }
+
+  name_of(midi_from_frequency(frequency, reference_a4).round() as i32)
+}
+
+// Name of a MIDI note number including its octave, e.g. "A4" for 69.
+pub fn midi_note_name(midi: u8) -> String {
+  name_of(midi as i32)
+}
+
+// A note name with a proper sharp sign for display, e.g. "A♯4" for "A#4".
+pub fn with_sharp_sign(note_name: &str) -> String {
+  note_name.replace('#', "\u{266f}")
+}
+
+// Like note_name(), but with a proper sharp sign for display, e.g. "A♯4".
+pub fn scientific_notation(frequency: f32, reference_a4: f32) -> String {
+  with_sharp_sign(&note_name(frequency, reference_a4))
+}
+
+// Movable-do solfege syllable of a pitch class (0 for C up to 11 for B) in the key whose tonic
+// has pitch class key, e.g. "sol" for G (7) in C major (0) or for D (2) in G major (7).
+pub fn solfege_of_pitch_class(pitch_class: usize, key: usize) -> String {
+  let degree = (pitch_class % 12 + 12 - key % 12) % 12;
+
+  String::from(SOLFEGE_SYLLABLES[degree])
+}
+
+// solfege_of_pitch_class() of the nearest equal-tempered note. Returns an empty string for
+// non-positive frequencies.
+pub fn solfege(frequency: f32, reference_a4: f32, key: usize) -> String {
+  if frequency <= 0.0 {
+    return String::new();
+  }
+
+  solfege_of_pitch_class(pitch_class(frequency, reference_a4), key)
+}
+
+// Equal-tempered interval from a to b as the nearest whole number of semitones (negative when b
+// is lower) plus the remaining cents (-50..=50), e.g. (7, 1.96) for a just fifth.
+pub fn interval_between(a: f32, b: f32) -> (i32, f32) {
+  let semitones = 12.0 * (b / a).log2();
+  let whole_semitones = semitones.round();
+
+  (
+    whole_semitones as i32,
+    (semitones - whole_semitones) * 100.0,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  mod midi_from_frequency {
+    use super::*;
+
+    #[test]
+    fn reference_pitch_is_a4() {
+      assert_eq!(midi_from_frequency(440.0, DEFAULT_REFERENCE_A4), 69.0);
+    }
+
+    #[test]
+    fn octave_is_twelve_semitones() {
+      assert_eq!(midi_from_frequency(880.0, DEFAULT_REFERENCE_A4), 81.0);
+      assert_eq!(midi_from_frequency(220.0, DEFAULT_REFERENCE_A4), 57.0);
+    }
+  }
+
+  mod pitch_class {
+    use super::*;
+
+    #[test]
+    fn ignores_octave() {
+      assert_eq!(pitch_class(440.0, DEFAULT_REFERENCE_A4), 9);
+      assert_eq!(pitch_class(55.0, DEFAULT_REFERENCE_A4), 9);
+      assert_eq!(pitch_class(261.63, DEFAULT_REFERENCE_A4), 0);
+      assert_eq!(pitch_class(246.94, DEFAULT_REFERENCE_A4), 11);
+    }
+  }
+
+  mod frequency_from_midi {
+    use super::*;
+
+    #[test]
+    fn a4_is_reference_pitch() {
+      assert_eq!(frequency_from_midi(69.0, DEFAULT_REFERENCE_A4), 440.0);
+      assert_eq!(frequency_from_midi(69.0, 415.0), 415.0);
+    }
+
+    #[test]
+    fn inverts_midi_from_frequency() {
+      let midi = midi_from_frequency(261.63, DEFAULT_REFERENCE_A4);
+
+      assert!((frequency_from_midi(midi, DEFAULT_REFERENCE_A4) - 261.63).abs() < 0.01);
+    }
+  }
+
+  mod nearest_midi_note {
+    use super::*;
+
+    #[test]
+    fn rounds_to_nearest_note() {
+      assert_eq!(nearest_midi_note(440.0, DEFAULT_REFERENCE_A4), 69);
+      assert_eq!(nearest_midi_note(445.0, DEFAULT_REFERENCE_A4), 69);
+      assert_eq!(nearest_midi_note(261.63, DEFAULT_REFERENCE_A4), 60);
+    }
+
+    #[test]
+    fn clamps_to_midi_range() {
+      assert_eq!(nearest_midi_note(1.0, DEFAULT_REFERENCE_A4), 0);
+      assert_eq!(nearest_midi_note(100000.0, DEFAULT_REFERENCE_A4), 127);
+    }
+  }
+
+  mod cents_from_nearest_note {
+    use super::*;
+
+    #[test]
+    fn zero_when_in_tune() {
+      assert!(cents_from_nearest_note(440.0, DEFAULT_REFERENCE_A4).abs() < 0.01);
+    }
+
+    #[test]
+    fn positive_when_sharp() {
+      let cents = cents_from_nearest_note(445.0, DEFAULT_REFERENCE_A4);
+
+      assert!((cents - 19.56).abs() < 0.1);
+    }
+
+    #[test]
+    fn negative_when_flat() {
+      let cents = cents_from_nearest_note(435.0, DEFAULT_REFERENCE_A4);
+
+      assert!((cents + 19.79).abs() < 0.1);
+    }
+  }
+
+  mod note_name {
+    use super::*;
+
+    #[test]
+    fn names_notes() {
+      assert_eq!(note_name(440.0, DEFAULT_REFERENCE_A4), "A4");
+      assert_eq!(note_name(261.63, DEFAULT_REFERENCE_A4), "C4");
+      assert_eq!(note_name(523.25, DEFAULT_REFERENCE_A4), "C5");
+      assert_eq!(note_name(554.37, DEFAULT_REFERENCE_A4), "C#5");
+    }
+
+    #[test]
+    fn rounds_to_nearest_note() {
+      assert_eq!(note_name(445.0, DEFAULT_REFERENCE_A4), "A4");
+      assert_eq!(note_name(460.0, DEFAULT_REFERENCE_A4), "A#4");
+    }
+
+    #[test]
+    fn respects_reference_pitch() {
+      assert_eq!(note_name(415.0, 415.0), "A4");
+      assert_eq!(note_name(415.0, DEFAULT_REFERENCE_A4), "G#4");
+    }
+
+    #[test]
+    fn handles_very_low_notes() {
+      // MIDI note 0.
+      assert_eq!(note_name(8.18, DEFAULT_REFERENCE_A4), "C-1");
+    }
+
+    #[test]
+    fn empty_for_zero_frequency() {
+      assert_eq!(note_name(0.0, DEFAULT_REFERENCE_A4), "");
+    }
+
+    #[test]
+    fn names_midi_notes() {
+      assert_eq!(midi_note_name(69), "A4");
+      assert_eq!(midi_note_name(0), "C-1");
+      assert_eq!(midi_note_name(73), "C#5");
+    }
+  }
+
+  mod scientific_notation {
+    use super::*;
+
+    #[test]
+    fn uses_sharp_sign() {
+      assert_eq!(scientific_notation(440.0, DEFAULT_REFERENCE_A4), "A4");
+      assert_eq!(
+        scientific_notation(466.16, DEFAULT_REFERENCE_A4),
+        "A\u{266f}4"
+      );
+      assert_eq!(
+        scientific_notation(8.66, DEFAULT_REFERENCE_A4),
+        "C\u{266f}-1"
+      );
+      assert_eq!(scientific_notation(0.0, DEFAULT_REFERENCE_A4), "");
+    }
+  }
+
+  mod solfege {
+    use super::*;
+
+    #[test]
+    fn names_degrees_of_c_major() {
+      let syllables: Vec<String> = [261.63, 293.66, 329.63, 349.23, 392.0, 440.0, 493.88, 523.25]
+        .iter()
+        .map(|frequency| solfege(*frequency, DEFAULT_REFERENCE_A4, 0))
+        .collect();
+
+      assert_eq!(
+        syllables,
+        vec!["do", "re", "mi", "fa", "sol", "la", "ti", "do"]
+      );
+    }
+
+    #[test]
+    fn is_relative_to_key() {
+      // G major.
+      assert_eq!(solfege(392.0, DEFAULT_REFERENCE_A4, 7), "do");
+      assert_eq!(solfege(293.66, DEFAULT_REFERENCE_A4, 7), "sol");
+      assert_eq!(solfege(369.99, DEFAULT_REFERENCE_A4, 7), "ti");
+      // A raised degree in C.
+      assert_eq!(solfege(369.99, DEFAULT_REFERENCE_A4, 0), "fi");
+    }
+
+    #[test]
+    fn empty_for_zero_frequency() {
+      assert_eq!(solfege(0.0, DEFAULT_REFERENCE_A4, 0), "");
+    }
+  }
+
+  mod interval_between {
+    use super::*;
+
+    #[test]
+    fn octave_is_twelve_semitones() {
+      let (semitones, cents) = interval_between(440.0, 880.0);
+
+      assert_eq!(semitones, 12);
+      assert!(cents.abs() < 0.01);
+    }
+
+    #[test]
+    fn just_fifth_is_slightly_wide() {
+      let (semitones, cents) = interval_between(440.0, 660.0);
+
+      assert_eq!(semitones, 7);
+      assert!((cents - 1.96).abs() < 0.1);
+    }
+
+    #[test]
+    fn descending_interval_is_negative() {
+      let (semitones, cents) = interval_between(660.0, 440.0);
+
+      assert_eq!(semitones, -7);
+      assert!((cents + 1.96).abs() < 0.1);
+    }
+
+    #[test]
+    fn unison_is_zero() {
+      assert_eq!(interval_between(440.0, 440.0), (0, 0.0));
+    }
+  }
+}
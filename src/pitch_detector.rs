@@ -1,5 +1,9 @@
+use super::fft_detector::FftDetector;
+use super::notes;
+use super::yin_detector::YinDetector;
 use pitch_detection;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use wasm_bindgen::prelude::*;
 
 extern crate web_sys;
@@ -26,6 +30,225 @@ fn fill_chunk(signal: &[f32], start: usize, window: usize, output: &mut [f32]) {
   }
 }
 
+// Low-pass filter taps (a Hann windowed sinc) removing frequencies that would alias when keeping
+// only every factor'th sample.
+fn anti_alias_taps(factor: usize) -> Vec<f32> {
+  let half = 4 * factor;
+  let cutoff = 0.5 / factor as f32;
+  let dx = std::f32::consts::PI / half as f32;
+
+  let taps: Vec<f32> = (0..=2 * half)
+    .map(|i| {
+      let x = i as f32 - half as f32;
+      let sinc = match i == half {
+        true => 2.0 * cutoff,
+        false => (2.0 * std::f32::consts::PI * cutoff * x).sin() / (std::f32::consts::PI * x),
+      };
+      sinc * (0.5 + 0.5 * (x * dx).cos())
+    })
+    .collect();
+
+  // Normalize for unity gain at DC.
+  let sum: f32 = taps.iter().sum();
+  taps.iter().map(|tap| tap / sum).collect()
+}
+
+// Like fill_chunk(), but filters with the taps and keeps every factor'th sample, so output holds
+// window samples covering window * factor samples of the signal.
+fn fill_downsampled_chunk(
+  signal: &[f32],
+  start: usize,
+  window: usize,
+  factor: usize,
+  taps: &[f32],
+  output: &mut [f32],
+) {
+  let half = taps.len() / 2;
+
+  for (i, sample) in output[0..window].iter_mut().enumerate() {
+    let centre = start + i * factor;
+
+    *sample = taps
+      .iter()
+      .enumerate()
+      .filter_map(|(k, tap)| {
+        (centre + k)
+          .checked_sub(half)
+          .and_then(|index| signal.get(index))
+          .map(|s| s * tap)
+      })
+      .sum();
+  }
+}
+
+// Root-mean-square amplitude of the samples.
+fn rms(samples: &[f32]) -> f32 {
+  if samples.is_empty() {
+    return 0.0;
+  }
+
+  (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+// Level reported for silence rather than -inf.
+pub const SILENCE_DB: f32 = -100.0;
+
+// Amplitude in decibels relative to full scale, floored at SILENCE_DB.
+fn decibels(rms: f32) -> f32 {
+  if rms <= 0.0 {
+    return SILENCE_DB;
+  }
+
+  (20.0 * rms.log10()).max(SILENCE_DB)
+}
+
+// Samples per block when following the level of the signal to locate an attack.
+const ONSET_BLOCK_SIZE: usize = 64;
+
+// Smallest ratio of block energies treated as an attack rather than fluctuation of a steady level.
+const ONSET_MIN_ENERGY_RATIO: f32 = 4.0;
+
+// Fractional index of the attack leading up to the loudest block of samples[start..end]: where
+// the level, interpolated between block centres, crosses halfway from the quietest preceding
+// block to the loudest. start if there is no attack or the level is already past halfway at
+// start.
+fn refine_onset(samples: &[f32], start: usize, end: usize) -> f32 {
+  let energies: Vec<f32> = samples[start..end]
+    .chunks_exact(ONSET_BLOCK_SIZE)
+    .map(|block| block.iter().map(|s| s * s).sum())
+    .collect();
+
+  let peak =
+    match (0..energies.len()).max_by(|a, b| energies[*a].partial_cmp(&energies[*b]).unwrap()) {
+      Some(peak) => peak,
+      None => return start as f32,
+    };
+  let floor = energies[0..=peak].iter().cloned().fold(f32::MAX, f32::min);
+  if floor * ONSET_MIN_ENERGY_RATIO > energies[peak] {
+    return start as f32;
+  }
+  let threshold = (floor + energies[peak]) / 2.0;
+
+  let mut crossing = peak;
+  while crossing > 0 && energies[crossing - 1] >= threshold {
+    crossing -= 1;
+  }
+
+  if crossing == 0 {
+    return start as f32;
+  }
+
+  let (before, after) = (energies[crossing - 1], energies[crossing]);
+  let fraction = (threshold - before) / (after - before);
+
+  start as f32 + (crossing as f32 - 0.5 + fraction) * ONSET_BLOCK_SIZE as f32
+}
+
+// How close (in cents) a pitch must be to exactly an octave above or below the current pitch
+// to be treated as an octave error.
+const OCTAVE_JUMP_TOLERANCE_CENTS: f32 = 25.0;
+
+// Folds a frequency that is an octave above or below the current pitch back onto it.
+fn correct_octave_jump(frequency: f32, current_pitch: f32) -> f32 {
+  let cents = 1200.0 * (frequency / current_pitch).log2();
+
+  if (cents - 1200.0).abs() < OCTAVE_JUMP_TOLERANCE_CENTS {
+    frequency / 2.0
+  } else if (cents + 1200.0).abs() < OCTAVE_JUMP_TOLERANCE_CENTS {
+    frequency * 2.0
+  } else {
+    frequency
+  }
+}
+
+// How strongly the samples must repeat after half the detected period, relative to after the
+// full period, for the detected pitch to be treated as a sub-harmonic of the true pitch.
+const SUBHARMONIC_CORRELATION_RATIO: f32 = 0.9;
+
+// Normalized correlation (-1..1) of the samples with themselves delayed by lag samples.
+fn normalized_correlation(samples: &[f32], lag: usize) -> f32 {
+  if lag == 0 || lag >= samples.len() {
+    return 0.0;
+  }
+
+  let (mut correlation, mut energy) = (0.0, 0.0);
+  for i in 0..samples.len() - lag {
+    correlation += samples[i] * samples[i + lag];
+    energy += samples[i] * samples[i] + samples[i + lag] * samples[i + lag];
+  }
+
+  match energy > 0.0 {
+    true => 2.0 * correlation / energy,
+    false => 0.0,
+  }
+}
+
+// Whether a pitch detected at frequency is likely half the true pitch, i.e. the samples repeat
+// (almost) as well after half the detected period as after the whole of it.
+fn is_subharmonic(samples: &[f32], sample_rate: f32, frequency: f32) -> bool {
+  let period = sample_rate / frequency;
+  let full = normalized_correlation(samples, period.round() as usize);
+  let half = normalized_correlation(samples, (period / 2.0).round() as usize);
+
+  full > 0.0 && half >= SUBHARMONIC_CORRELATION_RATIO * full
+}
+
+// Signal to noise ratio reported when there is no noise at all rather than +inf.
+pub const MAX_SNR_DB: f32 = 100.0;
+
+// Ratio in dB of the energy that repeats every period of frequency, i.e. lies at its harmonics,
+// to the energy that doesn't. Each sample is compared with the one a period later (interpolated
+// for fractional periods): half their sum is the periodic part, half their difference the
+// residual.
+fn harmonic_snr_db(samples: &[f32], sample_rate: f32, frequency: f32) -> f32 {
+  let period = sample_rate / frequency;
+  let lag = period.floor() as usize;
+  let fraction = period - lag as f32;
+
+  if lag == 0 || lag + 1 >= samples.len() {
+    return 0.0;
+  }
+
+  let (mut periodic, mut residual) = (0.0, 0.0);
+  for i in 0..samples.len() - lag - 1 {
+    let delayed = samples[i + lag] * (1.0 - fraction) + samples[i + lag + 1] * fraction;
+
+    periodic += (0.5 * (samples[i] + delayed)).powi(2);
+    residual += (0.5 * (samples[i] - delayed)).powi(2);
+  }
+
+  match residual > 0.0 {
+    true => (10.0 * (periodic / residual).log10()).min(MAX_SNR_DB),
+    false => MAX_SNR_DB,
+  }
+}
+
+// Tapering applied to each window of samples before detection.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WindowFunction {
+  Rectangular,
+  Hann,
+  Hamming,
+}
+
+fn apply_window_function(window_function: WindowFunction, chunk: &mut [f32]) {
+  let (a0, a1) = match window_function {
+    WindowFunction::Rectangular => return,
+    WindowFunction::Hann => (0.5, 0.5),
+    WindowFunction::Hamming => (0.54, 0.46),
+  };
+
+  if chunk.len() < 2 {
+    return;
+  }
+
+  let dx = 2.0 * std::f32::consts::PI / (chunk.len() - 1) as f32;
+  for (i, sample) in chunk.iter_mut().enumerate() {
+    *sample *= a0 - a1 * (i as f32 * dx).cos();
+  }
+}
+
 #[wasm_bindgen]
 #[derive(Copy, Clone)]
 pub struct Params {
@@ -34,6 +257,265 @@ pub struct Params {
   padding: usize,
   power_threshold: f32,
   clarity_threshold: f32,
+  window_function: WindowFunction,
+  hop_size: usize,
+  octave_correction: bool,
+  subharmonic_correction: bool,
+  onset_energy_rise_db: f32,
+  onset_debounce_ms: f32,
+  frequency_smoothing: f32,
+  confidence_floor: f32,
+  confidence_gamma: f32,
+  debug: bool,
+  reference_a4: f32,
+  downsample_factor: usize,
+}
+
+// 75% overlap between successive windows.
+fn default_hop_size(window: usize) -> usize {
+  (window / 4).max(1)
+}
+
+#[wasm_bindgen]
+impl Params {
+  #[wasm_bindgen(constructor)]
+  pub fn new(
+    window: usize,
+    sample_rate: usize,
+    power_threshold: f32,
+    clarity_threshold: f32,
+  ) -> Params {
+    Params {
+      window,
+      sample_rate,
+      padding: window / 2,
+      power_threshold,
+      clarity_threshold,
+      window_function: WindowFunction::Rectangular,
+      hop_size: default_hop_size(window),
+      octave_correction: false,
+      subharmonic_correction: false,
+      onset_energy_rise_db: 0.0,
+      onset_debounce_ms: 0.0,
+      frequency_smoothing: 0.0,
+      confidence_floor: 0.0,
+      confidence_gamma: 1.0,
+      debug: false,
+      reference_a4: notes::DEFAULT_REFERENCE_A4,
+      downsample_factor: 1,
+    }
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn sample_rate(&self) -> usize {
+    self.sample_rate
+  }
+
+  // Must match the rate of the incoming audio, otherwise detected frequencies are scaled by the
+  // ratio of the two rates.
+  #[wasm_bindgen(setter)]
+  pub fn set_sample_rate(&mut self, sample_rate: usize) {
+    self.sample_rate = sample_rate;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn padding(&self) -> usize {
+    self.padding
+  }
+
+  // Zero-padding the detector appends to each window, defaulting to half the window. Larger
+  // padding gives finer resolution at low frequencies at the cost of more processing. Takes
+  // effect for detectors created after it is set.
+  #[wasm_bindgen(setter)]
+  pub fn set_padding(&mut self, padding: usize) {
+    self.padding = padding;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn power_threshold(&self) -> f32 {
+    self.power_threshold
+  }
+
+  // Minimum signal power for a window to be considered for detection. Lower this for quiet
+  // instruments.
+  #[wasm_bindgen(setter)]
+  pub fn set_power_threshold(&mut self, power_threshold: f32) {
+    self.power_threshold = power_threshold;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn clarity_threshold(&self) -> f32 {
+    self.clarity_threshold
+  }
+
+  // Minimum clarity (0..1) a detected pitch must reach to be reported.
+  #[wasm_bindgen(setter)]
+  pub fn set_clarity_threshold(&mut self, clarity_threshold: f32) {
+    self.clarity_threshold = clarity_threshold;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn hop_size(&self) -> usize {
+    self.hop_size
+  }
+
+  // Number of samples between the starts of successive windows, defaulting to a quarter of the
+  // window. Must be between 1 and the window size. Smaller hops track fast passages more
+  // densely at the cost of more processing.
+  #[wasm_bindgen(setter)]
+  pub fn set_hop_size(&mut self, hop_size: usize) {
+    self.hop_size = hop_size;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn octave_correction(&self) -> bool {
+    self.octave_correction
+  }
+
+  // When enabled, a pitch that jumps by exactly an octave from the previous pitch of a
+  // continuing note is assumed to be a detector error and folded back. Off by default.
+  #[wasm_bindgen(setter)]
+  pub fn set_octave_correction(&mut self, octave_correction: bool) {
+    self.octave_correction = octave_correction;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn subharmonic_correction(&self) -> bool {
+    self.subharmonic_correction
+  }
+
+  // When enabled, each detected pitch is checked against the window's samples and doubled if the
+  // detector locked onto half the true frequency, as autocorrelation can for low strings. Unlike
+  // octave_correction this doesn't depend on the previous pitch, so it also corrects the first
+  // pitch of a note. Off by default.
+  #[wasm_bindgen(setter)]
+  pub fn set_subharmonic_correction(&mut self, subharmonic_correction: bool) {
+    self.subharmonic_correction = subharmonic_correction;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn onset_energy_rise_db(&self) -> f32 {
+    self.onset_energy_rise_db
+  }
+
+  // When above 0, a window whose level is at least this many decibels above the previous
+  // window's is flagged as an onset even though the sound didn't stop, catching re-articulated
+  // and slurred notes. Around 3 suits most instruments. 0 (the default) only reports onsets after
+  // a break in pitch.
+  #[wasm_bindgen(setter)]
+  pub fn set_onset_energy_rise_db(&mut self, onset_energy_rise_db: f32) {
+    self.onset_energy_rise_db = onset_energy_rise_db;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn onset_debounce_ms(&self) -> f32 {
+    self.onset_debounce_ms
+  }
+
+  // Minimum time between onsets. Onsets within this long of the previous one aren't flagged, so
+  // an unsteady note that briefly loses its pitch doesn't report a burst of new notes. Defaults
+  // to 0 (no debouncing).
+  #[wasm_bindgen(setter)]
+  pub fn set_onset_debounce_ms(&mut self, onset_debounce_ms: f32) {
+    self.onset_debounce_ms = onset_debounce_ms;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn frequency_smoothing(&self) -> f32 {
+    self.frequency_smoothing
+  }
+
+  // Weight (0..1) of the previous smoothed frequency in each pitch's frequency_smoothed, an
+  // exponential moving average over the note so far, e.g. 0.8 for a tuner needle that glides
+  // rather than jumps. Defaults to 0, where frequency_smoothed equals frequency.
+  #[wasm_bindgen(setter)]
+  pub fn set_frequency_smoothing(&mut self, frequency_smoothing: f32) {
+    self.frequency_smoothing = frequency_smoothing;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn confidence_floor(&self) -> f32 {
+    self.confidence_floor
+  }
+
+  // Clarity at and below which a pitch's confidence is 0. Defaults to 0.
+  #[wasm_bindgen(setter)]
+  pub fn set_confidence_floor(&mut self, confidence_floor: f32) {
+    self.confidence_floor = confidence_floor;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn confidence_gamma(&self) -> f32 {
+    self.confidence_gamma
+  }
+
+  // Exponent applied to the clarity above the floor, rescaled to 0..1: above 1 pulls mid
+  // clarities down, below 1 lifts them. Defaults to 1.
+  #[wasm_bindgen(setter)]
+  pub fn set_confidence_gamma(&mut self, confidence_gamma: f32) {
+    self.confidence_gamma = confidence_gamma;
+  }
+
+  // Maps a detector's clarity to a confidence (0..1) using confidence_floor and confidence_gamma,
+  // so each instrument can be calibrated for what its clean and unsteady notes read. With the
+  // defaults the confidence equals the clarity.
+  pub fn confidence(&self, clarity: f32) -> f32 {
+    let range = (1.0 - self.confidence_floor).max(f32::EPSILON);
+
+    ((clarity - self.confidence_floor) / range)
+      .clamp(0.0, 1.0)
+      .powf(self.confidence_gamma)
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn window_function(&self) -> WindowFunction {
+    self.window_function
+  }
+
+  // Defaults to rectangular (no tapering). Hann or Hamming reduce leakage at the window edges
+  // for sustained notes.
+  #[wasm_bindgen(setter)]
+  pub fn set_window_function(&mut self, window_function: WindowFunction) {
+    self.window_function = window_function;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn reference_a4(&self) -> f32 {
+    self.reference_a4
+  }
+
+  // Tuning reference for the MIDI notes and cents offsets of detected pitches. Defaults to
+  // 440 Hz; e.g. 442 for many orchestras or 415 for baroque pitch.
+  #[wasm_bindgen(setter)]
+  pub fn set_reference_a4(&mut self, reference_a4: f32) {
+    self.reference_a4 = reference_a4;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn downsample_factor(&self) -> usize {
+    self.downsample_factor
+  }
+
+  // Keep only every n'th sample (after low-pass filtering) before detection, so each window of
+  // the detector spans n times as many input samples. Improves accuracy for low notes that only
+  // fit a couple of periods in a window, at the cost of the highest detectable frequency and
+  // n times the latency. Defaults to 1 (no downsampling). Hop size stays in input samples.
+  #[wasm_bindgen(setter)]
+  pub fn set_downsample_factor(&mut self, downsample_factor: usize) {
+    self.downsample_factor = downsample_factor;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn debug(&self) -> bool {
+    self.debug
+  }
+
+  // Logs diagnostics (e.g. windows where no pitch was found) to the console. Off by default to
+  // avoid formatting a message for every silent window.
+  #[wasm_bindgen(setter)]
+  pub fn set_debug(&mut self, debug: bool) {
+    self.debug = debug;
+  }
 }
 
 pub fn make_params(
@@ -42,13 +524,7 @@ pub fn make_params(
   power_threshold: f32,
   clarity_threshold: f32,
 ) -> Params {
-  Params {
-    window,
-    sample_rate,
-    padding: window / 2,
-    power_threshold,
-    clarity_threshold,
-  }
+  Params::new(window, sample_rate, power_threshold, clarity_threshold)
 }
 
 #[wasm_bindgen]
@@ -61,21 +537,165 @@ pub struct PitchDetector {
   // produce stable pitches whenever there's ambiguity (between octaves for example).
   current_pitch: Option<f32>,
 
+  // Start of the current run of windows without a pitch, and the completed runs not yet
+  // returned from pitches().
+  rest_start: Option<usize>,
+  rests: Vec<Rest>,
+
+  // Start of the window of the most recently detected pitch, for measuring latency.
+  time_of_last_pitch: Option<usize>,
+
+  // Level in dBFS of the most recently analyzed window, for energy based onsets.
+  previous_window_db: Option<f32>,
+
+  // Start of the window of the most recent onset, for debouncing onsets.
+  time_of_last_onset: Option<usize>,
+
+  // Smoothed frequency of the current note, or None between notes.
+  smoothed_frequency: Option<f32>,
+
+  // Whether a pitch was detected in the most recently analyzed window.
+  last_window_pitched: bool,
+
   audio_samples: Vec<f32>,
 
+  // Kept so the detector can be rebuilt when the window changes, and for reporting the config.
+  detector_type: String,
   detector: Box<dyn pitch_detection::PitchDetector<f32>>,
   history: Option<pitch_detection::PitchDetectorHistory>,
+
+  // Tried on windows where detector finds no pitch, before treating them as rests.
+  fallback_detector_type: Option<String>,
+  fallback_detector: Option<Box<dyn pitch_detection::PitchDetector<f32>>>,
 }
 
 #[wasm_bindgen]
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Pitch {
+  // Time in seconds of the start of the analyzed window.
   pub t: f32,
   pub frequency: f32,
+  // Moving average of the frequency since the note's onset (see Params::frequency_smoothing).
+  pub frequency_smoothed: f32,
   pub clarity: f32,
+  // The clarity mapped through Params::confidence().
+  pub confidence: f32,
+  pub onset: bool,
+  // Time in seconds of the attack for onsets, located more precisely than the window start from
+  // the level of the samples around it. The same as t for other pitches.
+  pub onset_t: f32,
+
+  // Nearest equal-tempered note and the signed distance from it in cents (-50..=50).
+  pub midi_note: u8,
+  pub cents_offset: f32,
+
+  // Root-mean-square amplitude of the analyzed window, and the same in dBFS.
+  pub rms: f32,
+  pub db: f32,
+  // Energy at the pitch's harmonics relative to the rest in dB. Low values explain low clarity,
+  // e.g. a distant microphone or a noisy room.
+  pub snr_db: f32,
+
+  // Whether this is a rough estimate from a partial window (see PitchDetector::preview_pitch()).
+  pub preview: bool,
+}
+
+#[wasm_bindgen]
+impl Pitch {
+  // Name of the nearest equal-tempered note, e.g. "A4", from midi_note so that it agrees with
+  // midi_note and cents_offset whatever reference_a4 they were detected with. Empty for a pitch
+  // without a frequency.
+  pub fn note_name(&self) -> String {
+    if self.frequency <= 0.0 {
+      return String::new();
+    }
+
+    notes::midi_note_name(self.midi_note)
+  }
+
+  pub fn note_name_with_reference(&self, reference_a4: f32) -> String {
+    notes::note_name(self.frequency, reference_a4)
+  }
+
+  // t in milliseconds, for UI code that works in ms.
+  pub fn time_ms(&self) -> f32 {
+    self.t * 1000.0
+  }
+
+  // note_name() with a proper sharp sign, e.g. "A♯4", for display to learners.
+  pub fn scientific_notation(&self) -> String {
+    notes::with_sharp_sign(&self.note_name())
+  }
+
+  // Solfege syllable ("do", "re", "mi"...) of midi_note in the key with the given tonic pitch
+  // class (0 for C up to 11 for B, as from key::estimate_key()). Empty for a pitch without a
+  // frequency.
+  pub fn solfege(&self, key: usize) -> String {
+    if self.frequency <= 0.0 {
+      return String::new();
+    }
+
+    notes::solfege_of_pitch_class(self.midi_note as usize, key)
+  }
+}
+
+// Readable summary for logs and failed assertions, e.g. "A4 (440.4 Hz, clarity 0.95, onset)".
+impl fmt::Display for Pitch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} ({:.1} Hz, clarity {:.2}",
+      self.note_name(),
+      self.frequency,
+      self.clarity
+    )?;
+
+    if self.onset {
+      write!(f, ", onset")?;
+    }
+
+    if self.preview {
+      write!(f, ", preview")?;
+    }
+
+    write!(f, ")")
+  }
+}
+
+// A gap between pitches where the sound stopped or was too unclear to detect a pitch. Times are in
+// seconds, from the start of the first window without a pitch to the start of the window where a
+// pitch resumed.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rest {
+  pub start_t: f32,
+  pub end_t: f32,
+}
+
+// The analysis of a single window, whether or not it has a pitch, for displays that step
+// through the audio frame by frame.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisFrame {
+  // Time in seconds of the start of the window.
+  pub t: f32,
+  pitch: Option<Pitch>,
   pub onset: bool,
+  pub rms: f32,
+  pub db: f32,
+}
+
+#[wasm_bindgen]
+impl AnalysisFrame {
+  // The pitch detected in the window, if any.
+  #[wasm_bindgen(getter)]
+  pub fn pitch(&self) -> Option<Pitch> {
+    self.pitch
+  }
 }
 
+const DETECTOR_TYPES: [&str; 5] = ["Autocorrelation", "McLeod", "Smoothed McLeod", "YIN", "FFT"];
+
 fn make_detector(
   detector_type: String,
   params: Params,
@@ -93,338 +713,3766 @@ fn make_detector(
       params.window,
       params.padding,
     )),
+    "YIN" => Box::new(YinDetector::new(params.window)),
+    "FFT" => Box::new(FftDetector::new(params.window, params.padding)),
     _ => panic!(format!("unsupported detector type {}", detector_type)),
   }
 }
 
+fn make_detector_f64(
+  detector_type: String,
+  params: Params,
+) -> Box<dyn pitch_detection::PitchDetector<f64>> {
+  match detector_type.as_str() {
+    "Autocorrelation" => Box::new(pitch_detection::AutocorrelationDetector::<f64>::new(
+      params.window,
+      params.padding,
+    )),
+    "McLeod" => Box::new(pitch_detection::McLeodDetector::<f64>::new(
+      params.window,
+      params.padding,
+    )),
+    "Smoothed McLeod" => Box::new(pitch_detection::SmoothedMcLeodDetector::<f64>::new(
+      params.window,
+      params.padding,
+    )),
+    _ => panic!("unsupported f64 detector type {}", detector_type),
+  }
+}
+
+// Detects the pitch of single windows of f64 samples, for offline analysis of recordings where
+// rounding error accumulated over long windows matters. Audio from the browser is f32 to begin
+// with, and f64 doubles the memory and roughly halves the speed of detection, so live analysis
+// should use PitchDetector instead. YIN and FFT are only available in f32.
+pub struct PitchDetectorF64 {
+  params: Params,
+  detector: Box<dyn pitch_detection::PitchDetector<f64>>,
+}
+
+impl PitchDetectorF64 {
+  pub fn new(detector_type: String, params: Params) -> PitchDetectorF64 {
+    if params.window > MAX_WINDOW_SIZE {
+      panic!(
+        "PitchDetectorF64::new() window size exceeded maximum window size {}",
+        MAX_WINDOW_SIZE
+      );
+    }
+
+    PitchDetectorF64 {
+      params,
+      detector: make_detector_f64(detector_type, params),
+    }
+  }
+
+  // Frequency and clarity of the first window of the signal, or None if it's too quiet, unclear
+  // or shorter than a window.
+  pub fn get_pitch(&mut self, signal: &[f64]) -> Option<pitch_detection::Pitch<f64>> {
+    if signal.len() < self.params.window {
+      return None;
+    }
+
+    self.detector.get_pitch(
+      &signal[0..self.params.window],
+      self.params.sample_rate,
+      self.params.power_threshold as f64,
+      self.params.clarity_threshold as f64,
+      None,
+    )
+  }
+}
+
+// Why an analysis couldn't be carried out. The numeric values are stable, so JavaScript can
+// switch on them rather than matching code strings.
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnalysisError {
+  NotEnoughSamples = 1,
+  WindowTooLarge = 2,
+  InvalidHopSize = 3,
+  UnsupportedDetectorType = 4,
+  InvalidDownsampleFactor = 5,
+  SampleRangeOutOfBounds = 6,
+}
+
+impl AnalysisError {
+  // Name reported by PitchesResult::code() and given to errors thrown by try_new().
+  pub fn code(&self) -> &'static str {
+    match self {
+      AnalysisError::NotEnoughSamples => "not_enough_samples",
+      AnalysisError::WindowTooLarge => "window_too_large",
+      AnalysisError::InvalidHopSize => "invalid_hop_size",
+      AnalysisError::UnsupportedDetectorType => "unsupported_detector_type",
+      AnalysisError::InvalidDownsampleFactor => "invalid_downsample_factor",
+      AnalysisError::SampleRangeOutOfBounds => "sample_range_out_of_bounds",
+    }
+  }
+}
+
 #[wasm_bindgen]
 pub struct PitchesResult {
-  _code: String,
+  _error: Option<AnalysisError>,
   _message: String,
   _pitches: Vec<Pitch>,
+  _rests: Vec<Rest>,
+  _reference_a4: f32,
 }
 
 #[wasm_bindgen]
 impl PitchesResult {
-  pub fn from_error(code: String, message: String) -> PitchesResult {
+  pub fn from_error(error: AnalysisError, message: String) -> PitchesResult {
     PitchesResult {
-      _code: code,
+      _error: Some(error),
       _message: message,
       _pitches: Vec::new(),
+      _rests: Vec::new(),
+      _reference_a4: notes::DEFAULT_REFERENCE_A4,
     }
   }
 
   fn from_vec(pitches: Vec<Pitch>) -> PitchesResult {
     PitchesResult {
-      _code: String::from("success"),
+      _error: None,
       _message: String::from(""),
       _pitches: pitches,
+      _rests: Vec::new(),
+      _reference_a4: notes::DEFAULT_REFERENCE_A4,
+    }
+  }
+
+  fn with_rests(self, rests: Vec<Rest>) -> PitchesResult {
+    PitchesResult {
+      _rests: rests,
+      ..self
+    }
+  }
+
+  // The tuning reference used for summaries such as dominant_pitch().
+  fn with_reference_a4(self, reference_a4: f32) -> PitchesResult {
+    PitchesResult {
+      _reference_a4: reference_a4,
+      ..self
+    }
+  }
+
+  // A result with the same code, message and rests but different pitches.
+  fn with_pitches(&self, pitches: Vec<Pitch>) -> PitchesResult {
+    PitchesResult {
+      _error: self._error,
+      _message: self._message.clone(),
+      _pitches: pitches,
+      _rests: self._rests.clone(),
+      _reference_a4: self._reference_a4,
+    }
+  }
+
+  // "success", or the code of the error.
+  #[wasm_bindgen(getter)]
+  pub fn code(&self) -> String {
+    String::from(self._error.map_or("success", |error| error.code()))
+  }
+
+  // The error, or undefined on success.
+  #[wasm_bindgen(getter)]
+  pub fn error(&self) -> Option<AnalysisError> {
+    self._error
+  }
+
+  // The error's numeric value, or 0 on success.
+  #[wasm_bindgen(getter)]
+  pub fn error_code(&self) -> u32 {
+    self._error.map_or(0, |error| error as u32)
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn message(&self) -> String {
+    self._message.clone()
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn pitches(&self) -> js_sys::Array {
+    self
+      ._pitches
+      .clone()
+      .into_iter()
+      .map(JsValue::from)
+      .collect()
+  }
+
+  // Gaps between pitches that ended since the previous call to pitches().
+  #[wasm_bindgen(getter)]
+  pub fn rests(&self) -> js_sys::Array {
+    self._rests.clone().into_iter().map(JsValue::from).collect()
+  }
+
+  // Replaces each pitch's frequency with the median over a sliding window of `window` pitches
+  // centred on it, removing single-window jitter and octave spikes. Pitches near either end use
+  // as many neighbours as are available. The smoothed frequency and nearest note follow the
+  // median.
+  pub fn median_smoothed(&self, window: usize) -> PitchesResult {
+    let half = window / 2;
+    let len = self._pitches.len();
+
+    let smoothed = self
+      ._pitches
+      .iter()
+      .enumerate()
+      .map(|(i, pitch)| {
+        let start = i.saturating_sub(half);
+        let stop = (i + half + 1).min(len);
+
+        let mut frequencies: Vec<f32> = self._pitches[start..stop]
+          .iter()
+          .map(|p| p.frequency)
+          .collect();
+        frequencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mid = frequencies.len() / 2;
+        let median = match frequencies.len() % 2 {
+          0 => (frequencies[mid - 1] + frequencies[mid]) / 2.0,
+          _ => frequencies[mid],
+        };
+
+        Pitch {
+          frequency: median,
+          frequency_smoothed: median,
+          midi_note: notes::nearest_midi_note(median, self._reference_a4),
+          cents_offset: notes::cents_from_nearest_note(median, self._reference_a4),
+          ..*pitch
+        }
+      })
+      .collect();
+
+    self.with_pitches(smoothed)
+  }
+
+  // The pitches with each frequency snapped to the nearest equal-tempered note relative to
+  // reference_a4, e.g. for transcription. Other fields are unchanged apart from midi_note, which
+  // becomes the snapped note, and cents_offset, which becomes 0.
+  pub fn quantized(&self, reference_a4: f32) -> PitchesResult {
+    self.with_pitches(
+      self
+        ._pitches
+        .iter()
+        .map(|pitch| {
+          let midi = notes::midi_from_frequency(pitch.frequency, reference_a4).round();
+
+          Pitch {
+            frequency: notes::frequency_from_midi(midi, reference_a4),
+            midi_note: notes::nearest_midi_note(pitch.frequency, reference_a4),
+            cents_offset: 0.0,
+            ..*pitch
+          }
+        })
+        .collect(),
+    )
+  }
+
+  // Combines the pitches and rests of two results, e.g. from successive or overlapping buffers, in
+  // time order. Where both have a pitch at the same time only one is kept. If either result is an
+  // error it is returned instead, this one's first.
+  pub fn merge(self, other: PitchesResult) -> PitchesResult {
+    if self._error.is_some() {
+      return self;
+    }
+    if other._error.is_some() {
+      return other;
+    }
+
+    let mut pitches = self._pitches;
+    pitches.extend(other._pitches);
+    pitches.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    pitches.dedup_by(|a, b| a.t == b.t);
+
+    let mut rests = self._rests;
+    rests.extend(other._rests);
+    rests.sort_by(|a, b| a.start_t.partial_cmp(&b.start_t).unwrap());
+    rests.dedup();
+
+    PitchesResult {
+      _pitches: pitches,
+      _rests: rests,
+      ..self
+    }
+  }
+
+  // Only the pitches later than t (in seconds, like Pitch::t), e.g. for a poller that has kept
+  // earlier results and only wants what is new since the last pitch it saw.
+  pub fn after(&self, t: f32) -> PitchesResult {
+    self.with_pitches(
+      self
+        ._pitches
+        .iter()
+        .filter(|pitch| pitch.t > t)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  // One "t,frequency,clarity,onset" row per pitch, preceded by a header row.
+  pub fn to_csv(&self) -> String {
+    let mut csv = String::from("t,frequency,clarity,onset\n");
+
+    for pitch in self._pitches.iter() {
+      csv.push_str(&format!(
+        "{},{},{},{}\n",
+        pitch.t, pitch.frequency, pitch.clarity, pitch.onset
+      ));
+    }
+
+    csv
+  }
+
+  // Times (in seconds) of the pitches that begin a new note, e.g. for rhythm analysis.
+  pub fn onsets(&self) -> Vec<f32> {
+    self
+      ._pitches
+      .iter()
+      .filter(|p| p.onset)
+      .map(|p| p.t)
+      .collect()
+  }
+
+  // Average clarity of the pitches, or 0.0 if there are none.
+  pub fn mean_clarity(&self) -> f32 {
+    if self._pitches.is_empty() {
+      return 0.0;
+    }
+
+    self._pitches.iter().map(|p| p.clarity).sum::<f32>() / self._pitches.len() as f32
+  }
+
+  // A single pitch summarizing the result, e.g. of a sustained note: the clarity-weighted average
+  // frequency and mean clarity at the median time. None if there are no pitches.
+  pub fn dominant_pitch(&self) -> Option<Pitch> {
+    let total_clarity: f32 = self._pitches.iter().map(|p| p.clarity).sum();
+    if total_clarity <= 0.0 {
+      return None;
+    }
+
+    let frequency = self
+      ._pitches
+      .iter()
+      .map(|p| p.frequency * p.clarity)
+      .sum::<f32>()
+      / total_clarity;
+
+    let mut by_time = self._pitches.clone();
+    by_time.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    let median = by_time[by_time.len() / 2];
+
+    Some(Pitch {
+      frequency,
+      clarity: self.mean_clarity(),
+      midi_note: notes::nearest_midi_note(frequency, self._reference_a4),
+      cents_offset: notes::cents_from_nearest_note(frequency, self._reference_a4),
+      ..median
+    })
+  }
+
+  // Whether the pitches' mean clarity reaches the threshold, e.g. to hide a tuner reading during
+  // uncertain passages. Always false if there are no pitches.
+  pub fn is_confident(&self, threshold: f32) -> bool {
+    !self._pitches.is_empty() && self.mean_clarity() >= threshold
+  }
+
+  // Keeps pitches using a clarity gate with hysteresis: the gate opens at a pitch with clarity of
+  // at least enter and stays open while clarity stays at or above the lower exit, so a brief dip
+  // doesn't make a note flicker. An onset closes the gate, so each note must reach enter itself.
+  pub fn with_hysteresis(&self, enter: f32, exit: f32) -> PitchesResult {
+    let mut open = false;
+
+    self.with_pitches(
+      self
+        ._pitches
+        .iter()
+        .filter(|pitch| {
+          let threshold = match open && !pitch.onset {
+            true => exit,
+            false => enter,
+          };
+          open = pitch.clarity >= threshold;
+          open
+        })
+        .cloned()
+        .collect(),
+    )
+  }
+}
+
+// Fixed-size arrays can't be passed to JavaScript, so these are only available from Rust.
+impl PitchesResult {
+  // Clarity-weighted count of the pitches in each pitch class, indexed from C (0) to B (11), e.g.
+  // as input for estimating the key of a passage.
+  pub fn pitch_class_histogram(&self) -> [f32; 12] {
+    let mut histogram = [0.0; 12];
+
+    for pitch in self._pitches.iter().filter(|p| p.frequency > 0.0) {
+      histogram[notes::pitch_class(pitch.frequency, self._reference_a4)] += pitch.clarity;
+    }
+
+    histogram
+  }
+}
+
+// Error code and description of why a detector can't be created with these params, if it can't.
+fn check_params(params: &Params) -> Result<(), (AnalysisError, String)> {
+  if params.window > MAX_WINDOW_SIZE {
+    return Err((
+      AnalysisError::WindowTooLarge,
+      format!(
+        "window size exceeded maximum window size {}",
+        MAX_WINDOW_SIZE
+      ),
+    ));
+  }
+
+  if params.downsample_factor == 0 {
+    return Err((
+      AnalysisError::InvalidDownsampleFactor,
+      String::from("downsample factor must be at least 1"),
+    ));
+  }
+
+  // Each window spans window * downsample_factor input samples, which must also fit.
+  if params.window * params.downsample_factor > MAX_WINDOW_SIZE {
+    return Err((
+      AnalysisError::WindowTooLarge,
+      format!(
+        "window size {} downsampled by {} spans more than the maximum window size {}",
+        params.window, params.downsample_factor, MAX_WINDOW_SIZE
+      ),
+    ));
+  }
+
+  if params.hop_size == 0 || params.hop_size > params.window {
+    return Err((
+      AnalysisError::InvalidHopSize,
+      format!(
+        "hop size must be between 1 and the window size {}, got {}",
+        params.window, params.hop_size
+      ),
+    ));
+  }
+
+  Ok(())
+}
+
+fn check_detector_type(detector_type: &str) -> Result<(), (AnalysisError, String)> {
+  if !DETECTOR_TYPES.contains(&detector_type) {
+    return Err((
+      AnalysisError::UnsupportedDetectorType,
+      format!("unsupported detector type {}", detector_type),
+    ));
+  }
+
+  Ok(())
+}
+
+// Fluent alternative to make_params() and PitchDetector::new() for Rust callers, reporting
+// invalid configurations as errors rather than panicking. Unset values match make_params()
+// defaults for a McLeod detector.
+pub struct PitchDetectorBuilder {
+  detector_type: String,
+  window: usize,
+  sample_rate: usize,
+  power_threshold: f32,
+  clarity_threshold: f32,
+}
+
+impl Default for PitchDetectorBuilder {
+  fn default() -> PitchDetectorBuilder {
+    PitchDetectorBuilder::new()
+  }
+}
+
+impl PitchDetectorBuilder {
+  pub fn new() -> PitchDetectorBuilder {
+    PitchDetectorBuilder {
+      detector_type: String::from("McLeod"),
+      window: 2048,
+      sample_rate: 48000,
+      power_threshold: 0.25,
+      clarity_threshold: 0.6,
+    }
+  }
+
+  pub fn detector_type(mut self, detector_type: &str) -> PitchDetectorBuilder {
+    self.detector_type = String::from(detector_type);
+    self
+  }
+
+  pub fn window(mut self, window: usize) -> PitchDetectorBuilder {
+    self.window = window;
+    self
+  }
+
+  pub fn sample_rate(mut self, sample_rate: usize) -> PitchDetectorBuilder {
+    self.sample_rate = sample_rate;
+    self
+  }
+
+  pub fn power_threshold(mut self, power_threshold: f32) -> PitchDetectorBuilder {
+    self.power_threshold = power_threshold;
+    self
+  }
+
+  pub fn clarity_threshold(mut self, clarity_threshold: f32) -> PitchDetectorBuilder {
+    self.clarity_threshold = clarity_threshold;
+    self
+  }
+
+  pub fn build(self) -> Result<PitchDetector, String> {
+    check_detector_type(&self.detector_type).map_err(|(_, message)| message)?;
+
+    if self.sample_rate == 0 {
+      return Err(String::from("sample rate must be greater than 0"));
+    }
+
+    let params = make_params(
+      self.window,
+      self.sample_rate,
+      self.power_threshold,
+      self.clarity_threshold,
+    );
+    check_params(&params).map_err(|(_, message)| message)?;
+
+    Ok(PitchDetector::new(self.detector_type, params))
+  }
+}
+
+#[wasm_bindgen]
+impl PitchDetector {
+  pub fn new(detector_type: String, params: Params) -> PitchDetector {
+    if let Err((_, message)) = check_params(&params) {
+      panic!("PitchDetector::new() {}", message);
+    }
+
+    PitchDetector {
+      time_of_first_sample: 0,
+      time_of_next_unprocessed_sample: 0,
+      current_pitch: None,
+      rest_start: None,
+      rests: vec![],
+      time_of_last_pitch: None,
+      previous_window_db: None,
+      time_of_last_onset: None,
+      smoothed_frequency: None,
+      last_window_pitched: false,
+      audio_samples: vec![],
+
+      params,
+
+      detector: make_detector(detector_type.clone(), params),
+      detector_type,
+      history: None,
+
+      fallback_detector_type: None,
+      fallback_detector: None,
+    }
+  }
+
+  // Like new(), but an unsupported detector type or invalid window or hop size is returned as an
+  // Error named with its code ("unsupported_detector_type", "window_too_large" or
+  // "invalid_hop_size") rather than aborting, so callers can e.g. retry with a smaller window.
+  pub fn try_new(detector_type: String, params: Params) -> Result<PitchDetector, JsValue> {
+    check_detector_type(&detector_type)
+      .and_then(|_| check_params(&params))
+      .map_err(|(error, message)| {
+        let js_error = js_sys::Error::new(&message);
+        js_error.set_name(error.code());
+        JsValue::from(js_error)
+      })?;
+
+    Ok(PitchDetector::new(detector_type, params))
+  }
+
+  // The configuration in effect, e.g. for logging from the browser console when results don't
+  // match expectations.
+  #[wasm_bindgen(getter)]
+  pub fn detector_type(&self) -> String {
+    self.detector_type.clone()
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn window(&self) -> usize {
+    self.params.window
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn fallback_detector_type(&self) -> Option<String> {
+    self.fallback_detector_type.clone()
+  }
+
+  // A second algorithm to try on windows where the main detector finds no pitch, e.g.
+  // "Autocorrelation" behind "McLeod", to improve recall on difficult signals. Panics on an
+  // unsupported detector type, as new() does.
+  pub fn set_fallback_detector_type(&mut self, detector_type: String) {
+    self.fallback_detector = Some(make_detector(detector_type.clone(), self.params));
+    self.fallback_detector_type = Some(detector_type);
+  }
+
+  pub fn clear_fallback_detector_type(&mut self) {
+    self.fallback_detector = None;
+    self.fallback_detector_type = None;
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn sample_rate(&self) -> usize {
+    self.params.sample_rate
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn power_threshold(&self) -> f32 {
+    self.params.power_threshold
+  }
+
+  #[wasm_bindgen(getter)]
+  pub fn clarity_threshold(&self) -> f32 {
+    self.params.clarity_threshold
+  }
+
+  // Returns a "not_enough_samples" error result (and leaves the detector untouched) if fewer
+  // than a window of samples are provided, so callers can skip the frame and try again later.
+  pub fn set_audio_samples(
+    &mut self,
+    time_of_first_sample: usize,
+    audio_samples: Vec<f32>,
+  ) -> PitchesResult {
+    if let Err(result) = self.start_audio_samples_at(
+      "set_audio_samples()",
+      time_of_first_sample,
+      audio_samples.len(),
+    ) {
+      return result;
+    }
+
+    self.audio_samples = audio_samples;
+
+    PitchesResult::from_vec(Vec::new())
+  }
+
+  // Like set_audio_samples(), but copies the samples into the detector's existing buffer rather
+  // than taking ownership of a new one, so calling it every frame doesn't allocate once the
+  // buffer has grown to fit.
+  pub fn set_audio_samples_from_slice(
+    &mut self,
+    time_of_first_sample: usize,
+    audio_samples: &[f32],
+  ) -> PitchesResult {
+    if let Err(result) = self.start_audio_samples_at(
+      "set_audio_samples_from_slice()",
+      time_of_first_sample,
+      audio_samples.len(),
+    ) {
+      return result;
+    }
+
+    self.audio_samples.clear();
+    self.audio_samples.extend_from_slice(audio_samples);
+
+    PitchesResult::from_vec(Vec::new())
+  }
+
+  // Checks there are enough new samples and, if so, moves the start of the buffer to
+  // time_of_first_sample ready for them to be stored.
+  fn start_audio_samples_at(
+    &mut self,
+    caller: &str,
+    time_of_first_sample: usize,
+    num_samples: usize,
+  ) -> Result<(), PitchesResult> {
+    if self.params.debug {
+      console_log!("audio_samples.len() {}", num_samples);
+    }
+
+    if num_samples < self.samples_per_window() {
+      return Err(PitchesResult::from_error(
+        AnalysisError::NotEnoughSamples,
+        format!(
+          "{} insufficient audio samples to analyze. Got {}, need: {} samples",
+          caller,
+          num_samples,
+          self.samples_per_window()
+        ),
+      ));
+    }
+
+    self.time_of_first_sample = time_of_first_sample;
+
+    if time_of_first_sample > self.time_of_next_unprocessed_sample {
+      self.time_of_next_unprocessed_sample = time_of_first_sample;
+    }
+
+    Ok(())
+  }
+
+  // Appends samples that follow on from those already held, first discarding any that have
+  // already been analyzed. Unlike set_audio_samples() this avoids recopying the whole buffer for
+  // every chunk of a live stream.
+  pub fn push_samples(&mut self, new_samples: &[f32]) {
+    self.discard_samples_before(self.index_of_next_unprocessed_sample());
+
+    self.audio_samples.extend_from_slice(new_samples);
+  }
+
+  // Discards already analyzed samples to bound memory use, keeping at least one window's worth
+  // so that pitches() still has samples to work with.
+  pub fn compact(&mut self) {
+    let keep_from = self.index_of_next_unprocessed_sample().min(
+      self
+        .audio_samples
+        .len()
+        .saturating_sub(self.samples_per_window()),
+    );
+
+    self.discard_samples_before(keep_from);
+  }
+
+  // Returns the detector to its freshly constructed state, ready for a new recording, without
+  // reallocating the underlying detector.
+  pub fn reset(&mut self) {
+    self.audio_samples.clear();
+    self.time_of_first_sample = 0;
+    self.time_of_next_unprocessed_sample = 0;
+    self.current_pitch = None;
+    self.rest_start = None;
+    self.rests.clear();
+    self.time_of_last_pitch = None;
+    self.previous_window_db = None;
+    self.time_of_last_onset = None;
+    self.smoothed_frequency = None;
+    self.last_window_pitched = false;
+    self.history = None;
+  }
+
+  // Analyzes samples from just before the audio of interest, e.g. the first moment of a
+  // recording, without returning pitches or rests from them, so the first pitches returned
+  // carry on from the note already sounding rather than starting afresh. Buffered samples and
+  // timestamps are unaffected.
+  pub fn prime(&mut self, samples: &[f32]) {
+    let audio_samples = std::mem::replace(&mut self.audio_samples, samples.to_vec());
+    let time_of_first_sample = self.time_of_first_sample;
+    let time_of_next_unprocessed_sample = self.time_of_next_unprocessed_sample;
+    let time_of_last_pitch = self.time_of_last_pitch;
+    let time_of_last_onset = self.time_of_last_onset;
+    let rest_start = self.rest_start;
+    let num_rests = self.rests.len();
+
+    // The priming samples are timed from 0, so times from the buffered audio don't apply to them.
+    self.time_of_first_sample = 0;
+    self.time_of_next_unprocessed_sample = 0;
+    self.time_of_last_onset = None;
+    self.frames_vec();
+
+    self.audio_samples = audio_samples;
+    self.time_of_first_sample = time_of_first_sample;
+    self.time_of_next_unprocessed_sample = time_of_next_unprocessed_sample;
+    self.time_of_last_pitch = time_of_last_pitch;
+    self.time_of_last_onset = time_of_last_onset;
+    self.rest_start = rest_start;
+    self.rests.truncate(num_rests);
+  }
+
+  // Switches to a new window size, keeping buffered samples and the position of the next window
+  // to process. Padding and hop size return to their defaults for the new window. Panics if the
+  // window exceeds MAX_WINDOW_SIZE.
+  pub fn set_window(&mut self, window: usize) {
+    let mut params = self.params;
+    params.window = window;
+    params.padding = window / 2;
+    params.hop_size = default_hop_size(window);
+
+    if let Err((_, message)) = check_params(&params) {
+      panic!("PitchDetector::set_window() {}", message);
+    }
+
+    self.params = params;
+    self.detector = make_detector(self.detector_type.clone(), params);
+    self.fallback_detector = self
+      .fallback_detector_type
+      .clone()
+      .map(|detector_type| make_detector(detector_type, params));
+    // History from the previous detector doesn't apply to the new one.
+    self.history = None;
+  }
+
+  // Flags the next detected pitch as an onset, e.g. when the caller knows a new note has started
+  // from a MIDI keyboard or a manual tap.
+  pub fn mark_onset(&mut self) {
+    self.current_pitch = None;
+  }
+
+  // Whether a pitch is currently sounding, i.e. the next detected pitch won't be an onset.
+  pub fn has_current_pitch(&self) -> bool {
+    self.current_pitch.is_some()
+  }
+
+  // Whether the most recently analyzed window had a pitch, e.g. for gating UI while the sound is
+  // unpitched. Unlike has_current_pitch() this isn't cleared by onsets.
+  pub fn is_pitched(&self) -> bool {
+    self.last_window_pitched
+  }
+
+  // Samples between the start of the most recently detected pitch's window and the latest
+  // sample, i.e. how far the pitch lags the audio. None until a pitch has been detected.
+  pub fn latency_samples(&self) -> Option<usize> {
+    let time_of_last_sample = self.time_of_first_sample + self.audio_samples.len();
+
+    self
+      .time_of_last_pitch
+      .map(|time_of_last_pitch| time_of_last_sample.saturating_sub(time_of_last_pitch))
+  }
+
+  // latency_samples() in milliseconds.
+  pub fn latency_ms(&self) -> Option<f32> {
+    self
+      .latency_samples()
+      .map(|samples| samples as f32 * 1000.0 / self.params.sample_rate as f32)
+  }
+
+  fn discard_samples_before(&mut self, index: usize) {
+    let index = index.min(self.audio_samples.len());
+
+    self.audio_samples.drain(..index);
+    self.time_of_first_sample += index;
+  }
+
+  pub fn index_of_next_unprocessed_sample(&self) -> usize {
+    self.time_of_next_unprocessed_sample - self.time_of_first_sample
+  }
+
+  pub fn num_audio_samples(&self) -> usize {
+    self.audio_samples.len()
+  }
+
+  // Input samples spanned by each window, which is more than the window when downsampling.
+  pub(crate) fn samples_per_window(&self) -> usize {
+    self.params.window * self.params.downsample_factor
+  }
+
+  // Number of windows the next call to pitches() will analyze, without analyzing them, e.g. to
+  // skip the call until there is enough new audio or to report progress.
+  pub fn pending_window_count(&self) -> usize {
+    self.window_count_before(self.audio_samples.len())
+  }
+
+  // Number of unprocessed windows using only the samples before index.
+  fn window_count_before(&self, index: usize) -> usize {
+    let samples_per_window = self.samples_per_window();
+    let num_unprocessed_samples = index.saturating_sub(self.index_of_next_unprocessed_sample());
+
+    if num_unprocessed_samples < samples_per_window {
+      return 0;
+    }
+
+    (num_unprocessed_samples - samples_per_window) / self.params.hop_size
+  }
+
+  pub(crate) fn pitches_vec(&mut self) -> Vec<Pitch> {
+    self
+      .frames_vec()
+      .into_iter()
+      .filter_map(|frame| frame.pitch)
+      .collect()
+  }
+
+  // Analyzes the same windows as pitches_vec(), returning a frame for every window whether or
+  // not a pitch was detected in it.
+  pub fn frames_vec(&mut self) -> Vec<AnalysisFrame> {
+    let num_windows = self.pending_window_count();
+
+    self.analyze_windows(num_windows)
+  }
+
+  // Analyzes the next num_windows unprocessed windows.
+  fn analyze_windows(&mut self, num_windows: usize) -> Vec<AnalysisFrame> {
+    let mut frames: Vec<AnalysisFrame> = Vec::new();
+
+    let window_samples = self.params.window;
+    let samples_per_window = self.samples_per_window();
+    let delta: usize = self.params.hop_size;
+
+    if num_windows == 0 {
+      return frames;
+    }
+
+    // The chunk is our working memory.
+    let mut chunk = vec![0.0; MAX_WINDOW_SIZE];
+
+    let downsample_factor = self.params.downsample_factor;
+    let taps = match downsample_factor > 1 {
+      true => anti_alias_taps(downsample_factor),
+      false => vec![],
+    };
+
+    let index_of_next_unprocessed_sample = self.index_of_next_unprocessed_sample();
+
+    let detector = self.detector.as_mut();
+    let mut fallback_detector = self.fallback_detector.as_deref_mut();
+
+    for i in 0..num_windows {
+      let index: usize = i * delta + index_of_next_unprocessed_sample;
+      match downsample_factor > 1 {
+        true => fill_downsampled_chunk(
+          &self.audio_samples,
+          index,
+          window_samples,
+          downsample_factor,
+          &taps,
+          &mut chunk,
+        ),
+        false => fill_chunk(&self.audio_samples, index, window_samples, &mut chunk),
+      }
+      apply_window_function(self.params.window_function, &mut chunk[0..window_samples]);
+
+      let optional_pitch = match (
+        detector.get_pitch(
+          &chunk[0..window_samples],
+          self.params.sample_rate,
+          self.params.power_threshold,
+          self.params.clarity_threshold,
+          self.history,
+        ),
+        fallback_detector.as_mut(),
+      ) {
+        (None, Some(fallback_detector)) => fallback_detector.get_pitch(
+          &chunk[0..window_samples],
+          self.params.sample_rate,
+          self.params.power_threshold,
+          self.params.clarity_threshold,
+          self.history,
+        ),
+        (pitch, _) => pitch,
+      };
+
+      let window_start = self.time_of_first_sample + index;
+      let sample_rate = self.params.sample_rate as f32;
+      let t = window_start as f32 / sample_rate;
+      let window_rms = rms(&chunk[0..window_samples]);
+      let window_db = decibels(window_rms);
+
+      // A sharp rise in level starts a new note even if the pitch carried on, so forget the
+      // current pitch as mark_onset() does.
+      if self.params.onset_energy_rise_db > 0.0 {
+        let rise = self
+          .previous_window_db
+          .map_or(0.0, |previous_db| window_db - previous_db);
+        if rise >= self.params.onset_energy_rise_db {
+          self.current_pitch = None;
+        }
+      }
+      self.previous_window_db = Some(window_db);
+
+      // Update next unprocessed sample.
+      self.time_of_next_unprocessed_sample += delta;
+      self.last_window_pitched = optional_pitch.is_some();
+
+      match optional_pitch {
+        Some(pitch) => {
+          // We detected a pitch.
+          let onset_debounce_ms = self.params.onset_debounce_ms;
+          let debouncing = match self.time_of_last_onset {
+            Some(time_of_last_onset) => {
+              (window_start.saturating_sub(time_of_last_onset) as f32 * 1000.0 / sample_rate)
+                < onset_debounce_ms
+            }
+            None => false,
+          };
+          let onset = match self.current_pitch {
+            Some(_current_pitch) => false,
+            None => !debouncing,
+          };
+          if onset {
+            self.time_of_last_onset = Some(window_start);
+          }
+
+          // The attack is after the start of the previous window, which wasn't an onset, and
+          // before the end of this one.
+          let onset_t = match onset {
+            true => {
+              let start = index.saturating_sub(delta);
+              let end = (index + samples_per_window).min(self.audio_samples.len());
+              (self.time_of_first_sample as f32 + refine_onset(&self.audio_samples, start, end))
+                / sample_rate
+            }
+            false => t,
+          };
+
+          let pitch_frequency = match self.params.subharmonic_correction
+            && is_subharmonic(&chunk[0..window_samples], sample_rate, pitch.frequency)
+          {
+            true => pitch.frequency * 2.0,
+            false => pitch.frequency,
+          };
+
+          // The detector saw the downsampled signal as if at the full sample rate.
+          let detected_frequency = pitch_frequency / downsample_factor as f32;
+
+          let frequency = match (self.params.octave_correction, self.current_pitch) {
+            (true, Some(current_pitch)) => correct_octave_jump(detected_frequency, current_pitch),
+            _ => detected_frequency,
+          };
+
+          self.current_pitch = Some(frequency);
+
+          let smoothing = self.params.frequency_smoothing;
+          let frequency_smoothed = match (onset, self.smoothed_frequency) {
+            (false, Some(previous)) => smoothing * previous + (1.0 - smoothing) * frequency,
+            _ => frequency,
+          };
+          self.smoothed_frequency = Some(frequency_smoothed);
+
+          if let Some(rest_start) = self.rest_start.take() {
+            self.rests.push(Rest {
+              start_t: rest_start as f32 / sample_rate,
+              end_t: window_start as f32 / sample_rate,
+            });
+          }
+
+          self.time_of_last_pitch = Some(window_start);
+
+          frames.push(AnalysisFrame {
+            t,
+            pitch: Some(Pitch {
+              clarity: pitch.clarity,
+              confidence: self.params.confidence(pitch.clarity),
+              frequency,
+              frequency_smoothed,
+              t,
+              onset: onset,
+              onset_t,
+              midi_note: notes::nearest_midi_note(frequency, self.params.reference_a4),
+              cents_offset: notes::cents_from_nearest_note(frequency, self.params.reference_a4),
+              rms: window_rms,
+              db: window_db,
+              snr_db: harmonic_snr_db(&chunk[0..window_samples], sample_rate, pitch_frequency),
+              preview: false,
+            }),
+            onset,
+            rms: window_rms,
+            db: window_db,
+          })
+        }
+        None => {
+          // A break in the sound or sound quality has occurred. Next resumption will be onset
+          // of a new note.
+          self.current_pitch = None;
+          self.smoothed_frequency = None;
+
+          if self.rest_start.is_none() {
+            self.rest_start = Some(window_start);
+          }
+
+          if self.params.debug {
+            console_log!(
+              "no pitch calculated in window {}, t: {}, delta_t: {}, window: {}",
+              i,
+              window_start,
+              delta,
+              window_samples
+            );
+          }
+
+          frames.push(AnalysisFrame {
+            t,
+            pitch: None,
+            onset: false,
+            rms: window_rms,
+            db: window_db,
+          })
+        }
+      }
+    }
+
+    frames
+  }
+
+  // A rough pitch from the samples held while there are still fewer than a window of them, zero
+  // padded to a full window, so a tuner can show a reading before pitches() has anything to
+  // analyze. The clarity is scaled by the share of the window that was filled. Onsets and the
+  // windows later analyzed by pitches() are unaffected. None once there is a full window, or if
+  // no pitch was found.
+  pub fn preview_pitch(&mut self) -> Option<Pitch> {
+    let samples_per_window = self.samples_per_window();
+    let num_samples = self.audio_samples.len();
+
+    if num_samples == 0 || num_samples >= samples_per_window {
+      return None;
+    }
+
+    let window_samples = self.params.window;
+    let downsample_factor = self.params.downsample_factor;
+    let mut chunk = vec![0.0; window_samples];
+
+    match downsample_factor > 1 {
+      true => fill_downsampled_chunk(
+        &self.audio_samples,
+        0,
+        window_samples,
+        downsample_factor,
+        &anti_alias_taps(downsample_factor),
+        &mut chunk,
+      ),
+      false => fill_chunk(&self.audio_samples, 0, window_samples, &mut chunk),
+    }
+    apply_window_function(self.params.window_function, &mut chunk);
+
+    let params = self.params;
+    let history = self.history;
+    let pitch = self
+      .detector
+      .get_pitch(
+        &chunk,
+        params.sample_rate,
+        params.power_threshold,
+        params.clarity_threshold,
+        history,
+      )
+      .or_else(|| {
+        self.fallback_detector.as_mut()?.get_pitch(
+          &chunk,
+          params.sample_rate,
+          params.power_threshold,
+          params.clarity_threshold,
+          history,
+        )
+      })?;
+
+    let frequency = pitch.frequency / downsample_factor as f32;
+    let window_rms = rms(&chunk);
+
+    let t = self.time_of_first_sample as f32 / self.params.sample_rate as f32;
+    let clarity = pitch.clarity * num_samples as f32 / samples_per_window as f32;
+
+    Some(Pitch {
+      t,
+      frequency,
+      frequency_smoothed: frequency,
+      clarity,
+      confidence: self.params.confidence(clarity),
+      onset: false,
+      onset_t: t,
+      midi_note: notes::nearest_midi_note(frequency, self.params.reference_a4),
+      cents_offset: notes::cents_from_nearest_note(frequency, self.params.reference_a4),
+      rms: window_rms,
+      db: decibels(window_rms),
+      snr_db: harmonic_snr_db(&chunk, self.params.sample_rate as f32, pitch.frequency),
+      preview: true,
+    })
+  }
+
+  pub fn pitches(&mut self) -> PitchesResult {
+    if self.audio_samples.len() < self.samples_per_window() {
+      return PitchesResult::from_error(AnalysisError::NotEnoughSamples,
+        String::from(format!("pitches() requires at least {} samples and there are currently {}. Ensure set_audio_samples() has been called once enough samples are available.", self.samples_per_window(), self.audio_samples.len()))
+    );
+    }
+
+    let pitches = self.pitches_vec();
+
+    PitchesResult::from_vec(pitches)
+      .with_rests(self.rests.drain(..).collect())
+      .with_reference_a4(self.params.reference_a4)
+  }
+
+  // Like pitches(), but holds back windows within a window of the latest sample until more
+  // samples arrive, so a live display doesn't flicker with tentative readings at the end of the
+  // buffer.
+  pub fn finalized_pitches(&mut self) -> PitchesResult {
+    if self.audio_samples.len() < self.samples_per_window() {
+      return PitchesResult::from_error(
+        AnalysisError::NotEnoughSamples,
+        format!(
+          "finalized_pitches() requires at least {} samples and there are currently {}",
+          self.samples_per_window(),
+          self.audio_samples.len()
+        ),
+      );
+    }
+
+    let num_windows = self.window_count_before(
+      self
+        .audio_samples
+        .len()
+        .saturating_sub(self.samples_per_window()),
+    );
+    let pitches = self
+      .analyze_windows(num_windows)
+      .into_iter()
+      .filter_map(|frame| frame.pitch)
+      .collect();
+
+    PitchesResult::from_vec(pitches)
+      .with_rests(self.rests.drain(..).collect())
+      .with_reference_a4(self.params.reference_a4)
+  }
+}
+
+// Detects the pitches of a complete recording in one call, with default thresholds. Invalid
+// settings or a recording shorter than the window give an error result instead.
+#[wasm_bindgen]
+pub fn analyze_samples(
+  samples: &[f32],
+  detector_type: String,
+  window: usize,
+  sample_rate: usize,
+) -> PitchesResult {
+  let params = make_params(window, sample_rate, 0.25, 0.6);
+
+  if let Err((error, message)) =
+    check_detector_type(&detector_type).and_then(|_| check_params(&params))
+  {
+    return PitchesResult::from_error(error, message);
+  }
+
+  let mut detector = PitchDetector::new(detector_type, params);
+  let result = detector.set_audio_samples(0, samples.to_vec());
+  if result.error().is_some() {
+    return result;
+  }
+
+  detector.pitches()
+}
+
+#[cfg(test)]
+use super::test_utils;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_test_params(window: usize) -> Params {
+    Params::new(window, 48000, 0.25, 0.6)
+  }
+
+  // Reports a predetermined sequence of frequencies (None for no pitch), one per window.
+  struct ScriptedDetector {
+    frequencies: Vec<Option<f32>>,
+    next: usize,
+  }
+
+  impl pitch_detection::PitchDetector<f32> for ScriptedDetector {
+    fn get_pitch(
+      &mut self,
+      _signal: &[f32],
+      _sample_rate: usize,
+      _power_threshold: f32,
+      _clarity_threshold: f32,
+      _history: Option<pitch_detection::PitchDetectorHistory>,
+    ) -> Option<pitch_detection::Pitch<f32>> {
+      let frequency = self.frequencies.get(self.next).cloned().flatten();
+      self.next += 1;
+
+      frequency.map(|frequency| pitch_detection::Pitch {
+        frequency,
+        clarity: 0.9,
+      })
+    }
+  }
+
+  // A detector whose inner detector reports the given frequencies, with enough samples loaded
+  // for exactly that many windows.
+  fn make_scripted_detector(params: Params, frequencies: Vec<Option<f32>>) -> PitchDetector {
+    let num_samples = params.window + frequencies.len() * params.hop_size;
+
+    let mut detector = PitchDetector::new(String::from("McLeod"), params);
+    detector.detector = Box::new(ScriptedDetector {
+      frequencies,
+      next: 0,
+    });
+    detector.set_audio_samples(0, vec![0.0; num_samples]);
+    detector
+  }
+  mod adding_samples {
+    use super::*;
+
+    #[test]
+    fn errors_on_insufficient_samples() {
+      let result = PitchDetector::new(String::from("McLeod"), make_test_params(2))
+        .set_audio_samples(0, vec![]);
+
+      assert_eq!(result.code(), "not_enough_samples");
+      assert_eq!(result.error(), Some(AnalysisError::NotEnoughSamples));
+      assert_eq!(result.error_code(), 1);
+      assert_eq!(
+        result.message(),
+        "set_audio_samples() insufficient audio samples to analyze. Got 0, need: 2 samples"
+      );
+    }
+
+    #[test]
+    fn succeeds_on_sufficient_samples() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2));
+
+      let result = detector.set_audio_samples(0, vec![0.0; 2]);
+
+      assert_eq!(result.code(), "success");
+      assert_eq!(result.error(), None);
+      assert_eq!(result.error_code(), 0);
+      assert_eq!(detector.num_audio_samples(), 2);
+    }
+
+    #[test]
+    fn insufficient_samples_leave_existing_samples_untouched() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2));
+      detector.set_audio_samples(0, vec![0.0; 4]);
+
+      detector.set_audio_samples(4, vec![0.0; 1]);
+
+      assert_eq!(detector.num_audio_samples(), 4);
+      assert_eq!(detector.time_of_first_sample, 0);
+    }
+  }
+
+  mod detecting_pitches {
+    use super::*;
+
+    const WINDOW: usize = 2048;
+
+    fn sin_signal_samples(freq_hz: f32, duration_secs: f32) -> Vec<f32> {
+      const SAMPLE_RATE: usize = 48000;
+      let samples: usize = (SAMPLE_RATE as f32 * duration_secs) as usize;
+
+      test_utils::sin_signal(freq_hz, samples, SAMPLE_RATE)
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported detector type Not a real pitch detector type")]
+    fn panics_on_missing_detector_type() {
+      PitchDetector::new(
+        String::from("Not a real pitch detector type"),
+        make_test_params(4),
+      );
+    }
+
+    #[test]
+    fn detects_pitch_autocorrelation() {
+      let mut detector =
+        PitchDetector::new(String::from("Autocorrelation"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(440.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(format!("{:?}", pitches), "[Pitch { t: 0.0, frequency: 440.36697, frequency_smoothed: 440.36697, clarity: 0.94680345, confidence: 0.94680345, onset: true, onset_t: 0.0, midi_note: 69, cents_offset: 1.4434814, rms: 0.7073702, db: -3.0070643, snr_db: 51.643463, preview: false }, Pitch { t: 0.010666667, frequency: 440.36697, frequency_smoothed: 440.36697, clarity: 0.94702, confidence: 0.94702, onset: false, onset_t: 0.010666667, midi_note: 69, cents_offset: 1.4434814, rms: 0.7088358, db: -2.9890873, snr_db: 51.689796, preview: false }, Pitch { t: 0.021333333, frequency: 440.36697, frequency_smoothed: 440.36697, clarity: 0.9463327, confidence: 0.9463327, onset: false, onset_t: 0.021333333, midi_note: 69, cents_offset: 1.4434814, rms: 0.70421606, db: -3.0458817, snr_db: 51.56478, preview: false }, Pitch { t: 0.032, frequency: 440.36697, frequency_smoothed: 440.36697, clarity: 0.9471525, confidence: 0.9471525, onset: false, onset_t: 0.032, midi_note: 69, cents_offset: 1.4434814, rms: 0.7097384, db: -2.9780343, snr_db: 51.70773, preview: false }, Pitch { t: 0.042666666, frequency: 440.36697, frequency_smoothed: 440.36697, clarity: 0.9465997, confidence: 0.9465997, onset: false, onset_t: 0.042666666, midi_note: 69, cents_offset: 1.4434814, rms: 0.7059995, db: -3.0239124, snr_db: 51.616318, preview: false }]");
+    }
+
+    #[test]
+    fn detects_pitch_yin() {
+      let mut detector = PitchDetector::new(String::from("YIN"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(110.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches.len(), 5);
+      for pitch in pitches {
+        assert!((pitch.frequency - 110.0).abs() < 1.0);
+        assert!(pitch.clarity > 0.8);
+      }
+    }
+
+    #[test]
+    fn detects_pitch_fft() {
+      let mut detector = PitchDetector::new(String::from("FFT"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(440.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches.len(), 5);
+      for pitch in pitches {
+        assert!((pitch.frequency - 440.0).abs() < 2.0);
+        assert!(pitch.clarity > 0.9);
+      }
+    }
+
+    #[test]
+    fn detects_pitch_mcleod() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(format!("{:?}", pitches), "[Pitch { t: 0.0, frequency: 220.29074, frequency_smoothed: 220.29074, clarity: 0.894376, confidence: 0.894376, onset: true, onset_t: 0.0, midi_note: 57, cents_offset: 2.2865295, rms: 0.7099915, db: -2.974937, snr_db: 47.72564, preview: false }, Pitch { t: 0.010666667, frequency: 221.12888, frequency_smoothed: 221.12888, clarity: 0.89288074, confidence: 0.89288074, onset: false, onset_t: 0.010666667, midi_note: 57, cents_offset: 8.860779, rms: 0.7036123, db: -3.0533314, snr_db: 35.79972, preview: false }, Pitch { t: 0.021333333, frequency: 220.72627, frequency_smoothed: 220.72627, clarity: 0.89353347, confidence: 0.89353347, onset: false, onset_t: 0.021333333, midi_note: 57, cents_offset: 5.706024, rms: 0.7066461, db: -3.0159612, snr_db: 39.701283, preview: false }, Pitch { t: 0.032, frequency: 220.17342, frequency_smoothed: 220.17342, clarity: 0.8946273, confidence: 0.8946273, onset: false, onset_t: 0.032, midi_note: 57, cents_offset: 1.3641357, rms: 0.7109038, db: -2.9637828, snr_db: 52.237274, preview: false }, Pitch { t: 0.042666666, frequency: 220.95581, frequency_smoothed: 220.95581, clarity: 0.89314663, confidence: 0.89314663, onset: false, onset_t: 0.042666666, midi_note: 57, cents_offset: 7.5050354, rms: 0.7049098, db: -3.037329, snr_db: 37.27294, preview: false }]");
+    }
+
+    #[test]
+    fn returns_only_new_pitches() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+
+      // Get the available pitches.
+      /*let initial_pitches = */
+      detector.pitches_vec();
+      // println!("{:?}", initial_pitches);
+
+      // Call again. There should be no more to return.
+      let pitches = detector.pitches_vec();
+      assert_eq!(pitches.len(), 0);
+
+      detector.set_audio_samples(
+        detector.time_of_next_unprocessed_sample,
+        sin_signal_samples(220.0, 0.1),
+      );
+      let more_pitches = detector.pitches_vec();
+      assert_eq!(more_pitches.len(), 5);
+    }
+
+    #[test]
+    fn first_pitch_is_an_onset() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches[0].onset, true);
+    }
+
+    #[test]
+    fn second_pitch_is_not_an_onset() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches[1].onset, false);
+    }
+
+    #[test]
+    fn first_pitch_after_silence_is_an_onset() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      // Get first round of pitches.
+      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+      detector.pitches_vec();
+
+      // Add a some flat signal / noise where no pitches are generated.
+      detector.set_audio_samples(
+        detector.time_of_next_unprocessed_sample,
+        sin_signal_samples(0.0, 0.1),
+      );
+      detector.pitches_vec();
+
+      // Resumption of a signal that produces pitches.
+      detector.set_audio_samples(
+        detector.time_of_next_unprocessed_sample,
+        sin_signal_samples(440.0, 0.1),
+      );
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches[0].onset, true);
+    }
+  }
+
+  mod streaming {
+    use super::*;
+
+    const WINDOW: usize = 2048;
+    const CHUNK: usize = 128;
+
+    #[test]
+    fn pushing_chunks_matches_bulk_detection() {
+      let signal = test_utils::sin_signal(220.0, 9600, 48000);
+
+      let mut bulk = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      bulk.set_audio_samples(0, signal.clone());
+      let bulk_pitches = bulk.pitches_vec();
+
+      let mut streamed = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      let mut streamed_pitches = vec![];
+      for chunk in signal.chunks(CHUNK) {
+        streamed.push_samples(chunk);
+        streamed_pitches.extend(streamed.pitches_vec());
+      }
+
+      assert!(!bulk_pitches.is_empty());
+      assert_eq!(streamed_pitches, bulk_pitches);
+    }
+
+    #[test]
+    fn pushing_chunks_discards_processed_samples() {
+      let signal = test_utils::sin_signal(220.0, 9600, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      let mut total_pushed = 0;
+      for chunk in signal.chunks(CHUNK) {
+        detector.push_samples(chunk);
+        total_pushed += chunk.len();
+        detector.pitches_vec();
+
+        assert!(detector.num_audio_samples() <= WINDOW + detector.params.hop_size() + CHUNK);
+        // The held samples always end at the most recently pushed one.
+        assert_eq!(
+          detector.time_of_first_sample + detector.num_audio_samples(),
+          total_pushed
+        );
+      }
+    }
+  }
+
+  mod checking_params {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_params() {
+      assert_eq!(check_params(&make_test_params(2048)), Ok(()));
+    }
+
+    #[test]
+    fn oversized_window_is_an_error() {
+      assert_eq!(
+        check_params(&make_test_params(16384)),
+        Err((
+          AnalysisError::WindowTooLarge,
+          String::from("window size exceeded maximum window size 8192")
+        ))
+      );
+    }
+
+    #[test]
+    fn invalid_hop_size_is_an_error() {
+      let mut params = make_test_params(2048);
+      params.set_hop_size(4096);
+
+      assert_eq!(
+        check_params(&params).unwrap_err().0,
+        AnalysisError::InvalidHopSize
+      );
+    }
+
+    #[test]
+    fn unknown_detector_type_is_an_error() {
+      assert_eq!(check_detector_type("YIN"), Ok(()));
+      assert_eq!(
+        check_detector_type("Guess").unwrap_err().0,
+        AnalysisError::UnsupportedDetectorType
+      );
+    }
+  }
+
+  mod builder {
+    use super::*;
+
+    #[test]
+    fn builds_with_defaults() {
+      let detector = PitchDetectorBuilder::new().build().unwrap();
+
+      assert_eq!(detector.params.window, 2048);
+      assert_eq!(detector.params.sample_rate(), 48000);
+      assert_eq!(detector.params.power_threshold(), 0.25);
+      assert_eq!(detector.params.clarity_threshold(), 0.6);
+    }
+
+    #[test]
+    fn applies_chained_settings() {
+      let detector = PitchDetectorBuilder::new()
+        .detector_type("YIN")
+        .window(1024)
+        .sample_rate(44100)
+        .power_threshold(0.1)
+        .clarity_threshold(0.8)
+        .build()
+        .unwrap();
+
+      assert_eq!(detector.params.window, 1024);
+      assert_eq!(detector.params.sample_rate(), 44100);
+      assert_eq!(detector.params.power_threshold(), 0.1);
+      assert_eq!(detector.params.clarity_threshold(), 0.8);
+    }
+
+    #[test]
+    fn built_detector_detects_pitch() {
+      let mut detector = PitchDetectorBuilder::new().window(1024).build().unwrap();
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4800, 48000));
+
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      assert!((pitches[0].frequency - 440.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn errors_on_oversized_window() {
+      let result = PitchDetectorBuilder::new().window(16384).build();
+
+      assert_eq!(
+        result.err(),
+        Some(String::from(
+          "window size exceeded maximum window size 8192"
+        ))
+      );
+    }
+
+    #[test]
+    fn errors_on_empty_window() {
+      assert!(PitchDetectorBuilder::new().window(0).build().is_err());
+    }
+
+    #[test]
+    fn errors_on_unknown_detector_type() {
+      let result = PitchDetectorBuilder::new().detector_type("Guess").build();
+
+      assert_eq!(
+        result.err(),
+        Some(String::from("unsupported detector type Guess"))
+      );
+    }
+
+    #[test]
+    fn errors_on_zero_sample_rate() {
+      assert!(PitchDetectorBuilder::new().sample_rate(0).build().is_err());
+    }
+  }
+
+  mod analyzing_samples {
+    use super::*;
+
+    #[test]
+    fn detects_pitches_of_whole_buffer() {
+      let samples = test_utils::sin_signal(440.0, 9600, 48000);
+
+      let result = analyze_samples(&samples, String::from("McLeod"), 2048, 48000);
+
+      assert_eq!(result.code(), "success");
+      assert_eq!(result._pitches.len(), (9600 - 2048) / 512);
+      assert!(result.is_confident(0.8));
+      for pitch in result._pitches {
+        assert!((pitch.frequency - 440.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn errors_on_short_buffer() {
+      let samples = test_utils::sin_signal(440.0, 1000, 48000);
+
+      let result = analyze_samples(&samples, String::from("McLeod"), 2048, 48000);
+
+      assert_eq!(result.code(), "not_enough_samples");
+    }
+
+    #[test]
+    fn errors_on_invalid_settings() {
+      let samples = test_utils::sin_signal(440.0, 9600, 48000);
+
+      assert_eq!(
+        analyze_samples(&samples, String::from("Guess"), 2048, 48000).code(),
+        "unsupported_detector_type"
+      );
+      assert_eq!(
+        analyze_samples(&samples, String::from("McLeod"), 16384, 48000).code(),
+        "window_too_large"
+      );
+    }
+  }
+
+  mod changing_window {
+    use super::*;
+
+    #[test]
+    fn updates_window_derived_params() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      detector.set_window(1024);
+
+      assert_eq!(detector.params.window, 1024);
+      assert_eq!(detector.params.padding(), 512);
+      assert_eq!(detector.params.hop_size(), 256);
+    }
+
+    #[test]
+    fn later_detection_uses_new_window() {
+      let signal = test_utils::sin_signal(440.0, 9600, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.push_samples(&signal[0..4800]);
+      let before = detector.pitches_vec();
+
+      detector.set_window(1024);
+      detector.push_samples(&signal[4800..]);
+      let after = detector.pitches_vec();
+
+      assert!(!before.is_empty());
+      assert!(after.len() > 2);
+      // Windows now start a quarter of the new window apart, continuing from the last one.
+      assert_eq!(
+        after[0].t,
+        (before[before.len() - 1].t * 48000.0 + 512.0) / 48000.0
+      );
+      for pair in after.windows(2) {
+        assert!(((pair[1].t - pair[0].t) * 48000.0 - 256.0).abs() < 0.01);
+      }
+      for pitch in after {
+        assert!((pitch.frequency - 440.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    #[should_panic(
+      expected = "PitchDetector::set_window() window size exceeded maximum window size 8192"
+    )]
+    fn panics_on_oversized_window() {
+      PitchDetector::new(String::from("McLeod"), make_test_params(2048)).set_window(16384);
+    }
+  }
+
+  mod timestamps {
+    use super::*;
+
+    const WINDOW: usize = 2048;
+
+    fn assert_times_are_window_starts(pitches: &[Pitch], first_window_start: usize, hop: usize) {
+      for (i, pitch) in pitches.iter().enumerate() {
+        assert_eq!(pitch.t, (first_window_start + i * hop) as f32 / 48000.0);
+      }
+    }
+
+    #[test]
+    fn time_is_start_of_window() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      assert_times_are_window_starts(&pitches, 0, 512);
+    }
+
+    #[test]
+    fn time_is_consistent_across_calls() {
+      let signal = test_utils::sin_signal(220.0, 14400, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+
+      let mut pitches = vec![];
+      for end in [4800, 9600, 14400].iter() {
+        let time_of_first_sample = detector.time_of_next_unprocessed_sample;
+        detector.set_audio_samples(
+          time_of_first_sample,
+          signal[time_of_first_sample..*end].to_vec(),
+        );
+        pitches.extend(detector.pitches_vec());
+      }
+
+      assert!(pitches.len() > 10);
+      assert_times_are_window_starts(&pitches, 0, 512);
+    }
+
+    #[test]
+    fn time_is_offset_by_time_of_first_sample() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      detector.set_audio_samples(48000, test_utils::sin_signal(220.0, 4800, 48000));
+
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      assert_times_are_window_starts(&pitches, 48000, 512);
+    }
+
+    #[test]
+    fn time_in_milliseconds() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      detector.set_audio_samples(48000, test_utils::sin_signal(220.0, 4800, 48000));
+
+      let pitches = detector.pitches_vec();
+
+      assert_eq!(pitches[0].time_ms(), 1000.0);
+      assert!((pitches[1].time_ms() - 1000.0 - 512.0 / 48.0).abs() < 0.001);
+    }
+  }
+
+  mod compacting {
+    use super::*;
+
+    const WINDOW: usize = 2048;
+
+    #[test]
+    fn discards_processed_samples() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 9600, 48000));
+      detector.pitches_vec();
+      let time_of_next_unprocessed_sample = detector.time_of_next_unprocessed_sample;
+
+      detector.compact();
+
+      assert_eq!(detector.index_of_next_unprocessed_sample(), 0);
+      assert_eq!(
+        detector.time_of_first_sample,
+        time_of_next_unprocessed_sample
+      );
+      assert_eq!(
+        detector.num_audio_samples(),
+        9600 - time_of_next_unprocessed_sample
+      );
+    }
+
+    #[test]
+    fn keeps_at_least_one_window() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      detector.set_audio_samples(0, vec![0.0; WINDOW]);
+      detector.time_of_next_unprocessed_sample = WINDOW;
+
+      detector.compact();
+
+      assert_eq!(detector.num_audio_samples(), WINDOW);
+      assert_eq!(detector.time_of_first_sample, 0);
+    }
+
+    #[test]
+    fn stays_bounded_over_many_cycles() {
+      const CHUNK: usize = 1024;
+      let signal = test_utils::sin_signal(220.0, 48000, 48000);
+
+      let mut reference = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      reference.set_audio_samples(0, signal.clone());
+      let reference_frequencies: Vec<f32> = reference
+        .pitches_vec()
+        .iter()
+        .map(|p| p.frequency)
+        .collect();
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+      let mut frequencies = vec![];
+      for chunk in signal.chunks(CHUNK) {
+        detector.push_samples(chunk);
+        frequencies.extend(detector.pitches_vec().iter().map(|p| p.frequency));
+        detector.compact();
+
+        assert!(detector.num_audio_samples() <= WINDOW + CHUNK);
+      }
+
+      assert_eq!(frequencies, reference_frequencies);
+    }
+  }
+
+  mod onsets {
+    use super::*;
+
+    #[test]
+    fn has_current_pitch_while_tone_sounds() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      assert!(!detector.has_current_pitch());
+
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+
+      assert!(detector.has_current_pitch());
+    }
+
+    #[test]
+    fn is_pitched_follows_latest_window() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      assert!(!detector.is_pitched());
+
+      detector.push_samples(&test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+      assert!(detector.is_pitched());
+
+      detector.mark_onset();
+      assert!(detector.is_pitched());
+
+      detector.push_samples(&test_utils::new_real_buffer(4800));
+      detector.pitches_vec();
+      assert!(!detector.is_pitched());
+    }
+
+    #[test]
+    fn marking_onset_flags_next_pitch() {
+      let signal = test_utils::sin_signal(220.0, 9600, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      detector.push_samples(&signal[0..4800]);
+      detector.pitches_vec();
+
+      detector.mark_onset();
+      assert!(!detector.has_current_pitch());
+
+      detector.push_samples(&signal[4800..]);
+      let pitches = detector.pitches_vec();
+
+      assert!(pitches[0].onset);
+      assert!(!pitches[1].onset);
+    }
+
+    // Two notes played back to back without a break: a 220Hz note decaying to a quiet sustain,
+    // then a freshly attacked 247Hz note from sample 9600.
+    fn slurred_notes() -> Vec<f32> {
+      let mut signal = test_utils::enveloped_signal(220.0, 9600, 48000, 0.005, 0.05, 0.3, 0.0);
+      signal.extend(test_utils::enveloped_signal(
+        247.0, 9600, 48000, 0.005, 0.05, 0.3, 0.0,
+      ));
+      signal
+    }
+
+    #[test]
+    fn onset_time_is_refined_to_attack() {
+      // An attack halfway between two window starts.
+      let attack = 3000;
+      let mut signal = test_utils::new_real_buffer(attack);
+      signal.extend(test_utils::sin_signal(440.0, 4800, 48000));
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal);
+      let pitches = detector.pitches_vec();
+
+      let onset = pitches.iter().find(|p| p.onset).unwrap();
+      let window_error = (onset.t * 48000.0 - attack as f32).abs();
+      let refined_error = (onset.onset_t * 48000.0 - attack as f32).abs();
+      assert!(
+        refined_error < 16.0 && refined_error < window_error,
+        "attack at {}, window at {}",
+        onset.onset_t * 48000.0,
+        onset.t * 48000.0
+      );
+
+      assert!(pitches
+        .iter()
+        .filter(|p| !p.onset)
+        .all(|p| p.onset_t == p.t));
+    }
+
+    fn onset_times(onset_energy_rise_db: f32) -> Vec<usize> {
+      let mut params = make_test_params(2048);
+      params.set_onset_energy_rise_db(onset_energy_rise_db);
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.set_audio_samples(0, slurred_notes());
+      detector
+        .pitches_vec()
+        .iter()
+        .filter(|p| p.onset)
+        .map(|p| (p.t * 48000.0).round() as usize)
+        .collect()
+    }
+
+    #[test]
+    fn level_rise_flags_onset_without_a_break() {
+      let onsets = onset_times(3.0);
+
+      assert_eq!(onsets.len(), 2, "onsets {:?}", onsets);
+      assert_eq!(onsets[0], 0);
+      // The first window reaching into the second note.
+      assert!(
+        onsets[1] < 9600 && onsets[1] + 2048 > 9600,
+        "onsets {:?}",
+        onsets
+      );
+    }
+
+    fn onset_windows_with_debounce(onset_debounce_ms: f32) -> Vec<usize> {
+      let mut params = make_test_params(2048);
+      params.set_onset_debounce_ms(onset_debounce_ms);
+
+      // A pitch that keeps dropping out for a window at a time.
+      let frequencies = (0..20)
+        .map(|i| match i % 2 {
+          0 => Some(440.0),
+          _ => None,
+        })
+        .collect();
+
+      make_scripted_detector(params, frequencies)
+        .pitches_vec()
+        .iter()
+        .filter(|p| p.onset)
+        .map(|p| (p.t * 48000.0).round() as usize / 512)
+        .collect()
+    }
+
+    #[test]
+    fn debounce_limits_onset_rate() {
+      assert_eq!(
+        onset_windows_with_debounce(0.0),
+        vec![0, 2, 4, 6, 8, 10, 12, 14, 16, 18]
+      );
+
+      // 50ms is just under 5 windows of 512 samples at 48kHz.
+      assert_eq!(onset_windows_with_debounce(50.0), vec![0, 6, 12, 18]);
+    }
+
+    #[test]
+    fn level_rise_ignored_by_default() {
+      assert_eq!(onset_times(0.0), vec![0]);
+    }
+
+    #[test]
+    fn continuing_tone_without_marking_has_no_onset() {
+      let signal = test_utils::sin_signal(220.0, 9600, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      detector.push_samples(&signal[0..4800]);
+      detector.pitches_vec();
+
+      detector.push_samples(&signal[4800..]);
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches[0].onset);
+    }
+  }
+
+  mod rests {
+    use super::*;
+
+    // 0.1s of tone, 0.1s of silence, then 0.1s of tone.
+    fn tone_with_silent_middle() -> Vec<f32> {
+      let mut signal = test_utils::sin_signal(220.0, 4800, 48000);
+      signal.extend(test_utils::new_real_buffer(4800));
+      signal.extend(test_utils::sin_signal(220.0, 4800, 48000));
+      signal
+    }
+
+    #[test]
+    fn reports_gap_spanning_silence() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, tone_with_silent_middle());
+
+      let result = detector.pitches();
+
+      assert_eq!(result._rests.len(), 1);
+      let rest = result._rests[0];
+      // The first window without a pitch starts before the silence does, once most of it is
+      // silent, and the pitch resumes once the window reaches the second tone.
+      assert!(rest.start_t > 0.05 && rest.start_t <= 0.1);
+      assert!(rest.end_t > 0.15 && rest.end_t <= 0.2);
+    }
+
+    #[test]
+    fn no_gap_in_continuous_tone() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 14400, 48000));
+
+      assert!(detector.pitches()._rests.is_empty());
+    }
+
+    #[test]
+    fn gap_is_reported_once_sound_resumes() {
+      let mut detector = make_scripted_detector(
+        make_test_params(2048),
+        vec![Some(220.0), None, None, Some(220.0)],
+      );
+
+      assert_eq!(
+        detector.pitches()._rests,
+        vec![Rest {
+          start_t: 512.0 / 48000.0,
+          end_t: 1536.0 / 48000.0
+        }]
+      );
+    }
+
+    #[test]
+    fn open_gap_is_not_reported() {
+      let mut detector =
+        make_scripted_detector(make_test_params(2048), vec![Some(220.0), None, None]);
+
+      assert!(detector.pitches()._rests.is_empty());
+    }
+  }
+
+  mod latency {
+    use super::*;
+
+    #[test]
+    fn none_before_first_pitch() {
+      let detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      assert_eq!(detector.latency_samples(), None);
+      assert_eq!(detector.latency_ms(), None);
+    }
+
+    #[test]
+    fn is_about_a_window_and_hop_behind_latest_sample() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+
+      let latency = detector.latency_samples().unwrap();
+
+      // The last window started at 2048 with the latest sample at 4800.
+      assert_eq!(latency, 4800 - 2048);
+      assert!((2048 + 512..2048 + 2 * 512).contains(&latency));
+      assert_eq!(detector.latency_ms(), Some(2752.0 * 1000.0 / 48000.0));
+    }
+
+    #[test]
+    fn grows_while_no_pitch_is_detected() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+      let latency = detector.latency_samples().unwrap();
+
+      detector.push_samples(&test_utils::new_real_buffer(4800));
+      detector.pitches_vec();
+
+      // Windows overlapping the end of the tone still find its pitch, but none after it.
+      let silent_latency = detector.latency_samples().unwrap();
+      assert!(silent_latency > latency);
+      assert!(silent_latency >= 4800);
+    }
+
+    #[test]
+    fn cleared_by_reset() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+
+      detector.reset();
+
+      assert_eq!(detector.latency_samples(), None);
+    }
+  }
+
+  mod resetting {
+    use super::*;
+
+    #[test]
+    fn clears_state() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec();
+
+      detector.reset();
+
+      assert_eq!(detector.num_audio_samples(), 0);
+      assert_eq!(detector.time_of_first_sample, 0);
+      assert_eq!(detector.time_of_next_unprocessed_sample, 0);
+      assert!(detector.current_pitch.is_none());
+      assert!(detector.history.is_none());
+    }
+
+    #[test]
+    fn behaves_like_a_fresh_detector() {
+      let signal = test_utils::sin_signal(220.0, 4800, 48000);
+
+      let mut fresh = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      fresh.set_audio_samples(0, signal.clone());
+
+      let mut reused = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      reused.set_audio_samples(0, test_utils::sin_signal(440.0, 9600, 48000));
+      reused.pitches_vec();
+      reused.reset();
+      reused.set_audio_samples(0, signal);
+
+      assert_eq!(reused.pitches_vec(), fresh.pitches_vec());
+    }
+  }
+
+  mod debug_logging {
+    use super::*;
+
+    #[test]
+    fn off_by_default() {
+      assert!(!make_test_params(2048).debug());
+    }
+
+    #[test]
+    fn does_not_change_detection() {
+      let mut signal = test_utils::sin_signal(220.0, 4800, 48000);
+      signal.extend(test_utils::new_real_buffer(4800));
+
+      let mut quiet = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      quiet.set_audio_samples(0, signal.clone());
+
+      let mut debug_params = make_test_params(2048);
+      debug_params.set_debug(true);
+      let mut verbose = PitchDetector::new(String::from("McLeod"), debug_params);
+      verbose.set_audio_samples(0, signal);
+
+      assert_eq!(quiet.pitches_vec(), verbose.pitches_vec());
+    }
+  }
+
+  mod thresholds {
+    use super::*;
+
+    #[test]
+    fn params_new_matches_make_params() {
+      let params = Params::new(1024, 44100, 0.3, 0.7);
+
+      assert_eq!(params.window, 1024);
+      assert_eq!(params.padding, 512);
+      assert_eq!(params.power_threshold(), 0.3);
+      assert_eq!(params.clarity_threshold(), 0.7);
+    }
+
+    #[test]
+    fn setters_update_thresholds() {
+      let mut params = make_test_params(2048);
+
+      params.set_power_threshold(0.5);
+      params.set_clarity_threshold(0.9);
+
+      assert_eq!(params.power_threshold(), 0.5);
+      assert_eq!(params.clarity_threshold(), 0.9);
+    }
+
+    #[test]
+    fn stricter_clarity_threshold_rejects_pitches() {
+      let signal = test_utils::sin_signal(220.0, 4800, 48000);
+
+      let mut lenient_params = make_test_params(2048);
+      lenient_params.set_clarity_threshold(0.6);
+      let mut lenient = PitchDetector::new(String::from("McLeod"), lenient_params);
+      lenient.set_audio_samples(0, signal.clone());
+
+      let mut strict_params = make_test_params(2048);
+      strict_params.set_clarity_threshold(0.9);
+      let mut strict = PitchDetector::new(String::from("McLeod"), strict_params);
+      strict.set_audio_samples(0, signal);
+
+      assert!(strict.pitches_vec().len() < lenient.pitches_vec().len());
+    }
+  }
+
+  mod note_names {
+    use super::*;
+
+    fn pitch_at(frequency: f32) -> Pitch {
+      Pitch {
+        frequency,
+        clarity: 1.0,
+        midi_note: notes::nearest_midi_note(frequency, notes::DEFAULT_REFERENCE_A4),
+        ..Default::default()
+      }
+    }
+
+    #[test]
+    fn names_pitches() {
+      assert_eq!(pitch_at(440.0).note_name(), "A4");
+      assert_eq!(pitch_at(261.63).note_name(), "C4");
+      assert_eq!(pitch_at(523.25).note_name(), "C5");
+    }
+
+    #[test]
+    fn names_pitches_with_reference() {
+      assert_eq!(pitch_at(442.0).note_name_with_reference(442.0), "A4");
+    }
+
+    #[test]
+    fn empty_name_for_zero_frequency() {
+      assert_eq!(pitch_at(0.0).note_name(), "");
+    }
+
+    #[test]
+    fn name_follows_detection_reference() {
+      let mut params = make_test_params(2048);
+      params.set_reference_a4(415.0);
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.set_audio_samples(0, test_utils::sin_signal(415.0, 4800, 48000));
+
+      let pitch = detector.pitches_vec()[0];
+
+      assert_eq!(pitch.midi_note, 69);
+      assert_eq!(pitch.note_name(), "A4");
+      assert!(pitch.to_string().starts_with("A4 ("));
+    }
+
+    #[test]
+    fn learner_names_follow_detection_reference() {
+      let mut params = make_test_params(2048);
+      params.set_reference_a4(415.0);
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.set_audio_samples(0, test_utils::sin_signal(392.0, 4800, 48000));
+
+      // 392 Hz is G4 at A4 = 440 Hz but A♭4 (G♯4) at A4 = 415 Hz.
+      let pitch = detector.pitches_vec()[0];
+
+      assert_eq!(pitch.scientific_notation(), "G\u{266f}4");
+      assert_eq!(pitch.solfege(8), "do");
+    }
+
+    #[test]
+    fn names_pitches_for_learners() {
+      assert_eq!(pitch_at(466.16).scientific_notation(), "A\u{266f}4");
+      assert_eq!(pitch_at(440.0).solfege(0), "la");
+      assert_eq!(pitch_at(440.0).solfege(9), "do");
+    }
+
+    #[test]
+    fn displays_name_frequency_and_clarity() {
+      let pitch = Pitch {
+        clarity: 0.95,
+        ..pitch_at(440.4)
+      };
+
+      assert_eq!(pitch.to_string(), "A4 (440.4 Hz, clarity 0.95)");
+      assert_eq!(
+        Pitch {
+          onset: true,
+          ..pitch
+        }
+        .to_string(),
+        "A4 (440.4 Hz, clarity 0.95, onset)"
+      );
+    }
+  }
+
+  mod note_numbers {
+    use super::*;
+
+    #[test]
+    fn in_tune_pitch_has_no_cents_offset() {
+      let mut detector =
+        PitchDetector::new(String::from("Autocorrelation"), make_test_params(2048));
+
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4800, 48000));
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert_eq!(pitch.midi_note, 69);
+        assert!(pitch.cents_offset.abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn sharp_pitch_has_positive_cents_offset() {
+      let mut detector =
+        PitchDetector::new(String::from("Autocorrelation"), make_test_params(2048));
+
+      detector.set_audio_samples(0, test_utils::sin_signal(445.0, 4800, 48000));
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert_eq!(pitch.midi_note, 69);
+        assert!((pitch.cents_offset - 19.0).abs() < 5.0);
+      }
+    }
+  }
+
+  mod reference_pitch {
+    use super::*;
+
+    fn pitch_of_442_hz(reference_a4: f32) -> Pitch {
+      let mut params = make_test_params(2048);
+      params.set_reference_a4(reference_a4);
+
+      make_scripted_detector(params, vec![Some(442.0)]).pitches_vec()[0]
+    }
+
+    #[test]
+    fn defaults_to_440() {
+      assert_eq!(make_test_params(2048).reference_a4(), 440.0);
+    }
+
+    #[test]
+    fn in_tune_relative_to_reference() {
+      let pitch = pitch_of_442_hz(442.0);
+
+      assert_eq!(pitch.midi_note, 69);
+      assert!(pitch.cents_offset.abs() < 0.01);
+    }
+
+    #[test]
+    fn sharp_relative_to_440() {
+      let pitch = pitch_of_442_hz(440.0);
+
+      assert_eq!(pitch.midi_note, 69);
+      assert!((pitch.cents_offset - 7.85).abs() < 0.01);
+    }
+
+    #[test]
+    fn dominant_pitch_uses_reference() {
+      let mut params = make_test_params(2048);
+      params.set_reference_a4(442.0);
+      let mut detector = make_scripted_detector(params, vec![Some(442.0), Some(442.0)]);
+
+      let dominant = detector.pitches().dominant_pitch().unwrap();
+
+      assert!(dominant.cents_offset.abs() < 0.01);
+    }
+  }
+
+  mod window_functions {
+    use super::*;
+
+    #[test]
+    fn defaults_to_rectangular() {
+      assert_eq!(
+        make_test_params(2048).window_function(),
+        WindowFunction::Rectangular
+      );
+    }
+
+    #[test]
+    fn rectangular_leaves_samples_unchanged() {
+      let mut chunk = vec![1.0; 8];
+
+      apply_window_function(WindowFunction::Rectangular, &mut chunk);
+
+      assert_eq!(chunk, vec![1.0; 8]);
+    }
+
+    #[test]
+    fn hann_tapers_to_zero_at_edges() {
+      let mut chunk = vec![1.0; 9];
+
+      apply_window_function(WindowFunction::Hann, &mut chunk);
+
+      assert!(chunk[0].abs() < 1e-6);
+      assert!((chunk[4] - 1.0).abs() < 1e-6);
+      assert!(chunk[8].abs() < 1e-6);
+    }
+
+    #[test]
+    fn hamming_tapers_to_floor_at_edges() {
+      let mut chunk = vec![1.0; 9];
+
+      apply_window_function(WindowFunction::Hamming, &mut chunk);
+
+      assert!((chunk[0] - 0.08).abs() < 1e-6);
+      assert!((chunk[4] - 1.0).abs() < 1e-6);
+      assert!((chunk[8] - 0.08).abs() < 1e-6);
+    }
+
+    fn detect_220_hz(window_function: WindowFunction) -> Vec<Pitch> {
+      let mut params = make_test_params(2048);
+      params.set_window_function(window_function);
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      detector.pitches_vec()
+    }
+
+    #[test]
+    fn explicit_rectangular_matches_default() {
+      let mut default_detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      default_detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+
+      assert_eq!(
+        detect_220_hz(WindowFunction::Rectangular),
+        default_detector.pitches_vec()
+      );
+    }
+
+    #[test]
+    fn detects_pitch_with_hann_window() {
+      let pitches = detect_220_hz(WindowFunction::Hann);
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert!((pitch.frequency - 220.0).abs() < 2.0);
+      }
+    }
+
+    #[test]
+    fn detects_pitch_with_hamming_window() {
+      let pitches = detect_220_hz(WindowFunction::Hamming);
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert!((pitch.frequency - 220.0).abs() < 2.0);
+      }
+    }
+
+    #[test]
+    fn tapering_changes_clarity() {
+      let clarity_of = |window_function| detect_220_hz(window_function)[0].clarity;
+      let rectangular = clarity_of(WindowFunction::Rectangular);
+
+      // The taper reshapes the autocorrelation, so the same signal reads differently.
+      assert!((clarity_of(WindowFunction::Hann) - rectangular).abs() > 1e-3);
+      assert!((clarity_of(WindowFunction::Hamming) - rectangular).abs() > 1e-3);
+    }
+  }
+
+  mod pitches_result {
+    use super::*;
+
+    fn pitches_at(frequencies: &[f32]) -> Vec<Pitch> {
+      frequencies
+        .iter()
+        .enumerate()
+        .map(|(i, frequency)| Pitch {
+          t: i as f32,
+          frequency: *frequency,
+          clarity: 0.9,
+          onset: i == 0,
+          ..Default::default()
+        })
+        .collect()
+    }
+
+    fn frequencies(result: &PitchesResult) -> Vec<f32> {
+      result._pitches.iter().map(|p| p.frequency).collect()
+    }
+
+    fn pitches_with_clarities(clarities: &[f32]) -> Vec<Pitch> {
+      clarities
+        .iter()
+        .zip(pitches_at(&vec![440.0; clarities.len()]))
+        .map(|(clarity, pitch)| Pitch {
+          clarity: *clarity,
+          ..pitch
+        })
+        .collect()
+    }
+
+    fn times(result: &PitchesResult) -> Vec<f32> {
+      result._pitches.iter().map(|p| p.t).collect()
+    }
+
+    #[test]
+    fn hysteresis_keeps_note_through_brief_dip() {
+      let result = PitchesResult::from_vec(pitches_with_clarities(&[0.9, 0.95, 0.7, 0.9, 0.95]));
+
+      assert_eq!(
+        times(&result.with_hysteresis(0.85, 0.6)),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0]
+      );
+    }
+
+    #[test]
+    fn hysteresis_waits_for_enter_clarity() {
+      let result = PitchesResult::from_vec(pitches_with_clarities(&[0.7, 0.75, 0.9, 0.5, 0.7]));
+
+      assert_eq!(times(&result.with_hysteresis(0.85, 0.6)), vec![2.0]);
+    }
+
+    #[test]
+    fn pitch_class_histogram_weights_by_clarity() {
+      // C major arpeggio across octaves, plus a passing D with low clarity.
+      let mut pitches = pitches_at(&[261.63, 329.63, 392.0, 523.25, 659.26, 783.99, 293.66]);
+      pitches[6].clarity = 0.3;
+
+      let histogram = PitchesResult::from_vec(pitches).pitch_class_histogram();
+
+      assert!((histogram[0] - 1.8).abs() < 0.001);
+      assert!((histogram[4] - 1.8).abs() < 0.001);
+      assert!((histogram[7] - 1.8).abs() < 0.001);
+      assert!((histogram[2] - 0.3).abs() < 0.001);
+
+      let strongest = histogram.iter().cloned().fold(0.0, f32::max);
+      for (class, count) in histogram.iter().enumerate() {
+        if ![0, 4, 7].contains(&class) {
+          assert!(*count < strongest);
+        }
+      }
+    }
+
+    #[test]
+    fn merge_orders_by_time_without_duplicates() {
+      let first = PitchesResult::from_vec(pitches_at(&[220.0, 247.0, 262.0]));
+      let second = PitchesResult::from_vec(
+        pitches_at(&[294.0, 330.0, 349.0, 392.0])
+          .into_iter()
+          .map(|pitch| Pitch {
+            t: pitch.t + 1.5,
+            ..pitch
+          })
+          .collect(),
+      );
+
+      let merged = second.merge(first);
+
+      assert_eq!(times(&merged), vec![0.0, 1.0, 1.5, 2.0, 2.5, 3.5, 4.5]);
+      assert_eq!(
+        frequencies(&merged),
+        vec![220.0, 247.0, 294.0, 262.0, 330.0, 349.0, 392.0]
+      );
+    }
+
+    #[test]
+    fn merge_keeps_one_pitch_per_time() {
+      let merged = PitchesResult::from_vec(pitches_at(&[220.0, 247.0]))
+        .merge(PitchesResult::from_vec(pitches_at(&[220.0, 247.0, 262.0])));
+
+      assert_eq!(times(&merged), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn merge_surfaces_errors() {
+      let error = || PitchesResult::from_error(AnalysisError::NotEnoughSamples, String::new());
+      let success = || PitchesResult::from_vec(pitches_at(&[220.0]));
+
+      assert_eq!(success().merge(error()).code(), "not_enough_samples");
+      assert_eq!(error().merge(success()).code(), "not_enough_samples");
+      assert_eq!(success().merge(success()).code(), "success");
+    }
+
+    #[test]
+    fn after_keeps_only_later_pitches() {
+      let result = PitchesResult::from_vec(pitches_at(&[220.0, 247.0, 262.0, 294.0, 330.0]));
+
+      assert_eq!(frequencies(&result.after(2.0)), vec![294.0, 330.0]);
+      assert_eq!(frequencies(&result.after(-1.0)), frequencies(&result));
+      assert!(result.after(4.0)._pitches.is_empty());
+    }
+
+    #[test]
+    fn median_removes_single_window_spikes() {
+      let result = PitchesResult::from_vec(pitches_at(&[
+        220.0, 220.0, 440.0, 220.0, 220.0, 440.0, 220.0, 220.0,
+      ]));
+
+      assert_eq!(frequencies(&result.median_smoothed(3)), vec![220.0; 8]);
+    }
+
+    #[test]
+    fn median_of_alternating_sequence_follows_majority_of_neighbours() {
+      let result = PitchesResult::from_vec(pitches_at(&[220.0, 440.0, 220.0, 440.0, 220.0]));
+
+      assert_eq!(
+        frequencies(&result.median_smoothed(3)),
+        vec![330.0, 220.0, 440.0, 220.0, 330.0]
+      );
+    }
+
+    #[test]
+    fn median_preserves_other_fields() {
+      let pitches = pitches_at(&[220.0, 440.0, 220.0]);
+      let result = PitchesResult::from_vec(pitches.clone());
+
+      let smoothed = result.median_smoothed(3);
+
+      for (original, smoothed) in pitches.iter().zip(smoothed._pitches.iter()) {
+        assert_eq!(original.t, smoothed.t);
+        assert_eq!(original.clarity, smoothed.clarity);
+        assert_eq!(original.onset, smoothed.onset);
+      }
+    }
+
+    #[test]
+    fn median_updates_note_of_spike() {
+      let result = PitchesResult::from_vec(pitches_at(&[220.0, 450.0, 220.0]));
+
+      let spike = result.median_smoothed(3)._pitches[1];
+
+      assert_eq!(spike.frequency_smoothed, 220.0);
+      assert_eq!(spike.midi_note, 57);
+      assert!(spike.cents_offset.abs() < 0.01);
+    }
+
+    #[test]
+    fn median_of_single_window_is_unchanged() {
+      let result = PitchesResult::from_vec(pitches_at(&[220.0, 440.0, 230.0]));
+
+      assert_eq!(
+        frequencies(&result.median_smoothed(1)),
+        vec![220.0, 440.0, 230.0]
+      );
+    }
+
+    #[test]
+    fn csv_has_header_and_one_row_per_pitch() {
+      let result = PitchesResult::from_vec(pitches_at(&[220.5, 440.25]));
+
+      let csv = result.to_csv();
+
+      assert_eq!(csv.lines().count(), 3);
+      assert_eq!(
+        csv,
+        "t,frequency,clarity,onset\n0,220.5,0.9,true\n1,440.25,0.9,false\n"
+      );
+    }
+
+    #[test]
+    fn csv_of_empty_result_is_header_only() {
+      let result = PitchesResult::from_vec(vec![]);
+
+      assert_eq!(result.to_csv(), "t,frequency,clarity,onset\n");
+    }
+
+    #[test]
+    fn quantized_snaps_to_nearest_note() {
+      let result = PitchesResult::from_vec(pitches_at(&[443.0, 261.0]));
+
+      let quantized = result.quantized(notes::DEFAULT_REFERENCE_A4);
+
+      assert_eq!(quantized._pitches[0].frequency, 440.0);
+      assert!((quantized._pitches[1].frequency - 261.63).abs() < 0.01);
+      assert_eq!(quantized._pitches[1].cents_offset, 0.0);
+      assert_eq!(quantized._pitches[0].midi_note, 69);
+      assert_eq!(quantized._pitches[1].midi_note, 60);
+    }
+
+    #[test]
+    fn quantized_keeps_other_fields() {
+      let result = PitchesResult::from_vec(pitches_at(&[443.0, 445.0]));
+
+      let quantized = result.quantized(notes::DEFAULT_REFERENCE_A4);
+
+      for (original, snapped) in result._pitches.iter().zip(quantized._pitches.iter()) {
+        assert_eq!(snapped.t, original.t);
+        assert_eq!(snapped.clarity, original.clarity);
+        assert_eq!(snapped.onset, original.onset);
+      }
+      // The original is left unquantized.
+      assert_eq!(frequencies(&result), vec![443.0, 445.0]);
+    }
+
+    #[test]
+    fn median_keeps_error_code() {
+      let result = PitchesResult::from_error(AnalysisError::NotEnoughSamples, String::from(""));
+
+      assert_eq!(result.median_smoothed(3).code(), "not_enough_samples");
+    }
+
+    #[test]
+    fn onsets_are_times_of_note_starts() {
+      let mut signal = test_utils::sin_signal(220.0, 4800, 48000);
+      signal.extend(test_utils::new_real_buffer(4800));
+      signal.extend(test_utils::sin_signal(330.0, 4800, 48000));
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal);
+      let result = detector.pitches();
+      let onsets = result.onsets();
+
+      assert_eq!(onsets.len(), 2);
+      assert_eq!(onsets[0], 0.0);
+      // The second note is first detected once a window overlaps enough of it.
+      assert!(onsets[1] > 0.2 - 2048.0 / 48000.0 && onsets[1] <= 0.2);
+    }
+
+    #[test]
+    fn no_onsets_without_pitches() {
+      assert!(PitchesResult::from_vec(vec![]).onsets().is_empty());
+    }
+
+    #[test]
+    fn dominant_pitch_favours_clearer_pitches() {
+      let result = PitchesResult::from_vec(
+        [
+          (218.0, 0.2),
+          (219.0, 0.4),
+          (220.0, 1.0),
+          (221.0, 0.9),
+          (230.0, 0.1),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, (frequency, clarity))| Pitch {
+          t: i as f32 * 0.01,
+          frequency: *frequency,
+          clarity: *clarity,
+          ..Default::default()
+        })
+        .collect(),
+      );
+
+      let dominant = result.dominant_pitch().unwrap();
+
+      let unweighted_mean = (218.0 + 219.0 + 220.0 + 221.0 + 230.0) / 5.0;
+      assert!((dominant.frequency - 220.0).abs() < (unweighted_mean - 220.0_f32).abs());
+      assert!((dominant.frequency - 220.5).abs() < 0.5);
+      assert_eq!(dominant.t, 0.02);
+      assert_eq!(dominant.midi_note, 57);
+      assert!((dominant.clarity - 0.52).abs() < 0.001);
+    }
+
+    #[test]
+    fn no_dominant_pitch_without_pitches() {
+      assert_eq!(PitchesResult::from_vec(vec![]).dominant_pitch(), None);
+    }
+
+    fn result_with_clarities(clarities: &[f32]) -> PitchesResult {
+      PitchesResult::from_vec(
+        clarities
+          .iter()
+          .map(|clarity| Pitch {
+            frequency: 220.0,
+            clarity: *clarity,
+            ..Default::default()
+          })
+          .collect(),
+      )
+    }
+
+    #[test]
+    fn mean_clarity_averages_pitches() {
+      let result = result_with_clarities(&[0.5, 0.75, 1.0]);
+
+      assert!((result.mean_clarity() - 0.75).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn confident_only_when_mean_reaches_threshold() {
+      let result = result_with_clarities(&[0.5, 0.75, 1.0]);
+
+      assert!(result.is_confident(0.7));
+      assert!(result.is_confident(0.75));
+      assert!(!result.is_confident(0.8));
+    }
+
+    #[test]
+    fn empty_result_is_not_confident() {
+      let result = PitchesResult::from_vec(vec![]);
+
+      assert_eq!(result.mean_clarity(), 0.0);
+      assert!(!result.is_confident(0.0));
+    }
+  }
+
+  mod subharmonic_correction {
+    use super::*;
+
+    // Frequencies found in a tone by a detector that always reports 110Hz, as if it had locked
+    // onto the sub-harmonic of a 220Hz tone.
+    fn frequencies_from_subharmonic_detector(subharmonic_correction: bool, tone: f32) -> Vec<f32> {
+      let mut params = make_test_params(2048);
+      params.set_subharmonic_correction(subharmonic_correction);
+
+      let mut detector = make_scripted_detector(params, vec![Some(110.0); 4]);
+      detector.set_audio_samples(0, test_utils::sin_signal(tone, 2048 + 4 * 512, 48000));
+
+      detector.pitches_vec().iter().map(|p| p.frequency).collect()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+      assert!(!make_test_params(2048).subharmonic_correction());
+
+      assert_eq!(
+        frequencies_from_subharmonic_detector(false, 220.0),
+        vec![110.0; 4]
+      );
+    }
+
+    #[test]
+    fn corrects_to_true_fundamental() {
+      assert_eq!(
+        frequencies_from_subharmonic_detector(true, 220.0),
+        vec![220.0; 4]
+      );
+    }
+
+    #[test]
+    fn leaves_genuine_low_pitch_alone() {
+      assert_eq!(
+        frequencies_from_subharmonic_detector(true, 110.0),
+        vec![110.0; 4]
+      );
+    }
+  }
+
+  mod frequency_smoothing {
+    use super::*;
+
+    fn smoothed_frequencies(frequency_smoothing: f32, frequencies: Vec<Option<f32>>) -> Vec<f32> {
+      let mut params = make_test_params(2048);
+      params.set_frequency_smoothing(frequency_smoothing);
+
+      make_scripted_detector(params, frequencies)
+        .pitches_vec()
+        .iter()
+        .map(|p| p.frequency_smoothed)
+        .collect()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+      assert_eq!(make_test_params(2048).frequency_smoothing(), 0.0);
+
+      assert_eq!(
+        smoothed_frequencies(0.0, vec![Some(440.0), Some(466.0)]),
+        vec![440.0, 466.0]
+      );
+    }
+
+    #[test]
+    fn approaches_step_gradually() {
+      let mut frequencies = vec![Some(440.0); 3];
+      frequencies.extend(vec![Some(466.0); 8]);
+
+      let smoothed = smoothed_frequencies(0.5, frequencies);
+
+      assert_eq!(&smoothed[0..4], &[440.0, 440.0, 440.0, 453.0]);
+      assert!(smoothed[3..].windows(2).all(|pair| pair[0] < pair[1]));
+      assert!(smoothed[10] < 466.0 && smoothed[10] > 465.8);
+    }
+
+    #[test]
+    fn restarts_at_onset() {
+      assert_eq!(
+        smoothed_frequencies(0.5, vec![Some(440.0), None, Some(466.0)]),
+        vec![440.0, 466.0]
+      );
+    }
+
+    #[test]
+    fn restarts_after_debounced_gap() {
+      let mut params = make_test_params(2048);
+      params.set_frequency_smoothing(0.5);
+      // Long enough that the pitch after the gap isn't an onset.
+      params.set_onset_debounce_ms(1000.0);
+
+      let pitches =
+        make_scripted_detector(params, vec![Some(440.0), None, Some(466.0)]).pitches_vec();
+
+      assert!(!pitches[1].onset);
+      assert_eq!(pitches[1].frequency_smoothed, 466.0);
+    }
+  }
+
+  mod octave_correction {
+    use super::*;
+
+    fn frequencies_with_octave_correction(
+      octave_correction: bool,
+      frequencies: Vec<Option<f32>>,
+    ) -> Vec<f32> {
+      let mut params = make_test_params(2048);
+      params.set_octave_correction(octave_correction);
+
+      make_scripted_detector(params, frequencies)
+        .pitches_vec()
+        .iter()
+        .map(|p| p.frequency)
+        .collect()
+    }
+
+    #[test]
+    fn disabled_by_default() {
+      assert!(!make_test_params(2048).octave_correction());
+
+      assert_eq!(
+        frequencies_with_octave_correction(false, vec![Some(440.0), Some(880.0), Some(440.0)]),
+        vec![440.0, 880.0, 440.0]
+      );
+    }
+
+    #[test]
+    fn corrects_octave_above() {
+      assert_eq!(
+        frequencies_with_octave_correction(true, vec![Some(440.0), Some(880.0), Some(440.0)]),
+        vec![440.0, 440.0, 440.0]
+      );
+    }
+
+    #[test]
+    fn corrects_octave_below() {
+      assert_eq!(
+        frequencies_with_octave_correction(true, vec![Some(440.0), Some(220.0), Some(440.0)]),
+        vec![440.0, 440.0, 440.0]
+      );
+    }
+
+    #[test]
+    fn leaves_other_intervals_alone() {
+      assert_eq!(
+        frequencies_with_octave_correction(true, vec![Some(440.0), Some(660.0)]),
+        vec![440.0, 660.0]
+      );
+    }
+
+    #[test]
+    fn does_not_correct_across_a_new_onset() {
+      let mut params = make_test_params(2048);
+      params.set_octave_correction(true);
+
+      let pitches =
+        make_scripted_detector(params, vec![Some(440.0), None, Some(880.0)]).pitches_vec();
+
+      assert_eq!(pitches[1].frequency, 880.0);
+      assert!(pitches[1].onset);
+    }
+  }
+
+  mod volume {
+    use super::*;
+
+    #[test]
+    fn rms_of_full_scale_sine() {
+      let signal = test_utils::sin_signal(220.0, 4800, 48000);
+
+      assert!((rms(&signal) - 0.707).abs() < 0.001);
+    }
+
+    #[test]
+    fn rms_of_silence_is_zero() {
+      assert_eq!(rms(&test_utils::new_real_buffer(2048)), 0.0);
+      assert_eq!(rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn full_scale_sine_is_about_minus_3_db() {
+      assert!((decibels(0.707) + 3.01).abs() < 0.01);
+    }
+
+    #[test]
+    fn silence_is_floored() {
+      assert_eq!(decibels(0.0), SILENCE_DB);
+      assert_eq!(decibels(1e-9), SILENCE_DB);
+    }
+
+    #[test]
+    fn detected_pitches_carry_rms() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+
+      detector.set_audio_samples(0, test_utils::sin_signal(220.0, 4800, 48000));
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert!((pitch.rms - 0.707).abs() < 0.01);
+        assert!((pitch.db + 3.0).abs() < 0.2);
+      }
+    }
+  }
+
+  mod hop_size {
+    use super::*;
+
+    #[test]
+    fn defaults_to_quarter_window() {
+      assert_eq!(make_test_params(2048).hop_size(), 512);
+    }
+
+    #[test]
+    #[should_panic(expected = "hop size must be between 1 and the window size 2048, got 0")]
+    fn panics_on_zero_hop_size() {
+      let mut params = make_test_params(2048);
+      params.set_hop_size(0);
+
+      PitchDetector::new(String::from("McLeod"), params);
+    }
+
+    #[test]
+    #[should_panic(expected = "hop size must be between 1 and the window size 2048, got 2049")]
+    fn panics_on_hop_size_larger_than_window() {
+      let mut params = make_test_params(2048);
+      params.set_hop_size(2049);
+
+      PitchDetector::new(String::from("McLeod"), params);
+    }
+
+    #[test]
+    fn smaller_hop_produces_more_pitches() {
+      let signal = test_utils::sin_signal(220.0, 4800, 48000);
+
+      let mut default_detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      default_detector.set_audio_samples(0, signal.clone());
+
+      let mut params = make_test_params(2048);
+      params.set_hop_size(128);
+      let mut dense_detector = PitchDetector::new(String::from("McLeod"), params);
+      dense_detector.set_audio_samples(0, signal);
+
+      assert!(dense_detector.pitches_vec().len() > default_detector.pitches_vec().len());
+    }
+  }
+
+  mod sample_rate {
+    use super::*;
+
+    #[test]
+    fn setter_updates_sample_rate() {
+      let mut params = make_test_params(2048);
+
+      params.set_sample_rate(22050);
+
+      assert_eq!(params.sample_rate(), 22050);
+    }
+
+    #[test]
+    fn detects_pitch_at_44100() {
+      const SAMPLE_RATE: usize = 44100;
+      let mut detector = PitchDetector::new(
+        String::from("McLeod"),
+        make_params(2048, SAMPLE_RATE, 0.25, 0.6),
+      );
+
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4410, SAMPLE_RATE));
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        // Had the rate been assumed to be 48000 this would be ~479 Hz.
+        assert!((pitch.frequency - 440.0).abs() < 5.0);
+      }
+    }
+  }
+
+  mod test_signals {
+    use super::*;
+
+    fn mcleod_pitches(signal: Vec<f32>) -> PitchesResult {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal);
+      detector.pitches()
+    }
+
+    #[test]
+    fn noise_is_repeatable_for_a_seed() {
+      assert_eq!(
+        test_utils::white_noise(64, 7),
+        test_utils::white_noise(64, 7)
+      );
+      assert_ne!(
+        test_utils::white_noise(64, 7),
+        test_utils::white_noise(64, 8)
+      );
+    }
+
+    #[test]
+    fn noise_is_in_range() {
+      for sample in test_utils::white_noise(4096, 1) {
+        assert!((-1.0..1.0).contains(&sample));
+      }
+    }
+
+    #[test]
+    fn noisy_signal_has_requested_snr() {
+      let clean = test_utils::sin_signal(220.0, 48000, 48000);
+      let noisy = test_utils::sin_signal_with_noise(220.0, 48000, 48000, 20.0, 3);
+
+      let noise: Vec<f32> = noisy.iter().zip(clean.iter()).map(|(n, c)| n - c).collect();
+      let snr_db = 20.0 * (rms(&clean) / rms(&noise)).log10();
+
+      assert!((snr_db - 20.0).abs() < 0.5, "snr {}", snr_db);
+      assert_eq!(
+        noisy,
+        test_utils::sin_signal_with_noise(220.0, 48000, 48000, 20.0, 3)
+      );
+    }
+
+    #[test]
+    fn detects_pitch_at_high_snr() {
+      let result = mcleod_pitches(test_utils::sin_signal_with_noise(
+        220.0, 9600, 48000, 20.0, 3,
+      ));
+
+      assert_eq!(result._pitches.len(), (9600 - 2048) / 512);
+      for pitch in result._pitches {
+        assert!((pitch.frequency - 220.0).abs() < 2.0);
+      }
+    }
+
+    #[test]
+    fn detection_degrades_as_snr_falls() {
+      let clarities: Vec<f32> = [20.0, 0.0, -10.0]
+        .iter()
+        .map(|snr_db| {
+          let signal = test_utils::sin_signal_with_noise(220.0, 9600, 48000, *snr_db, 3);
+          let mut params = make_test_params(2048);
+          params.set_clarity_threshold(0.0);
+
+          let mut detector = PitchDetector::new(String::from("McLeod"), params);
+          detector.set_audio_samples(0, signal);
+          detector.pitches().mean_clarity()
+        })
+        .collect();
+
+      assert!(clarities[0] > clarities[1], "clarities {:?}", clarities);
+      assert!(clarities[1] > clarities[2], "clarities {:?}", clarities);
+    }
+
+    #[test]
+    fn detects_fundamental_of_sawtooth() {
+      let result = mcleod_pitches(test_utils::saw_signal(220.0, 9600, 48000));
+
+      assert!(!result._pitches.is_empty());
+      for pitch in result._pitches {
+        assert!((pitch.frequency - 220.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn detects_fundamental_of_square_wave() {
+      let result = mcleod_pitches(test_utils::square_signal(220.0, 9600, 48000));
+
+      assert!(!result._pitches.is_empty());
+      for pitch in result._pitches {
+        assert!((pitch.frequency - 220.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn detects_fundamental_of_harmonic_tone() {
+      let result = mcleod_pitches(test_utils::harmonic_signal(
+        220.0,
+        &[1.0, 0.5, 0.3],
+        9600,
+        48000,
+      ));
+
+      assert!(!result._pitches.is_empty());
+      for pitch in result._pitches {
+        assert!((pitch.frequency - 220.0).abs() < 5.0);
+      }
+    }
+
+    #[test]
+    fn harmonic_tone_with_single_amplitude_is_sine() {
+      assert_eq!(
+        test_utils::harmonic_signal(220.0, &[1.0], 256, 48000),
+        test_utils::sin_signal(220.0, 256, 48000)
+      );
+    }
+
+    #[test]
+    fn envelope_rises_from_and_falls_to_silence() {
+      let signal = test_utils::enveloped_signal(220.0, 4800, 48000, 0.01, 0.02, 0.5, 0.02);
+
+      assert_eq!(signal[0], 0.0);
+      assert!(signal.iter().all(|sample| sample.abs() <= 1.0));
+      assert!(signal[4700..].iter().all(|sample| sample.abs() < 0.05));
+    }
+
+    #[test]
+    fn enveloped_note_has_onset_then_fades_out() {
+      let mut signal = test_utils::new_real_buffer(2400);
+      signal.extend(test_utils::enveloped_signal(
+        220.0, 14400, 48000, 0.02, 0.05, 0.7, 0.1,
+      ));
+      signal.extend(test_utils::new_real_buffer(4800));
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal);
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      assert!(pitches[0].onset);
+      assert_eq!(pitches.iter().filter(|p| p.onset).count(), 1);
+
+      // Pitches get quieter through the release and stop before the trailing silence.
+      let loudest = pitches.iter().map(|p| p.rms).fold(0.0, f32::max);
+      assert!(pitches[pitches.len() - 1].rms < loudest / 2.0);
+      assert!(!detector.has_current_pitch());
+    }
+
+    #[test]
+    fn white_noise_has_no_confident_pitch() {
+      let result = mcleod_pitches(test_utils::white_noise(9600, 42));
+
+      assert!(!result.is_confident(0.9));
+    }
+  }
+
+  mod f64_detection {
+    use super::*;
+
+    #[test]
+    fn detects_440_at_least_as_accurately_as_f32() {
+      let mut f32_detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      f32_detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4096, 48000));
+      let f32_error = (f32_detector.pitches_vec()[0].frequency - 440.0).abs() as f64;
+
+      let mut f64_detector = PitchDetectorF64::new(String::from("McLeod"), make_test_params(2048));
+      let pitch = f64_detector
+        .get_pitch(&test_utils::sin_signal_f64(440.0, 2048, 48000))
+        .unwrap();
+      let f64_error = (pitch.frequency - 440.0).abs();
+
+      // Allow for f32 rounding in the comparison itself.
+      assert!(f64_error <= f32_error + 1e-3);
+    }
+
+    #[test]
+    fn none_for_short_signal() {
+      let mut detector = PitchDetectorF64::new(String::from("McLeod"), make_test_params(2048));
+
+      assert!(detector
+        .get_pitch(&test_utils::sin_signal_f64(440.0, 1024, 48000))
+        .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported f64 detector type YIN")]
+    fn yin_is_f32_only() {
+      PitchDetectorF64::new(String::from("YIN"), make_test_params(2048));
+    }
+
+    #[test]
+    #[should_panic(expected = "unsupported f64 detector type FFT")]
+    fn fft_is_f32_only() {
+      PitchDetectorF64::new(String::from("FFT"), make_test_params(2048));
+    }
+  }
+
+  mod padding {
+    use super::*;
+
+    #[test]
+    fn defaults_to_half_window() {
+      assert_eq!(make_test_params(2048).padding(), 1024);
+    }
+
+    #[test]
+    fn detects_bass_tone_with_different_paddings() {
+      for padding in [1024, 4096].iter() {
+        let mut params = make_test_params(4096);
+        params.set_padding(*padding);
+        let mut detector = PitchDetector::new(String::from("McLeod"), params);
+
+        detector.set_audio_samples(0, test_utils::sin_signal(110.0, 9600, 48000));
+        let pitches = detector.pitches_vec();
+
+        assert!(!pitches.is_empty(), "no pitches with padding {}", padding);
+        for pitch in pitches {
+          assert!((pitch.frequency - 110.0).abs() < 2.0);
+        }
+      }
     }
   }
 
-  #[wasm_bindgen(getter)]
-  pub fn code(&self) -> String {
-    self._code.clone()
-  }
+  mod downsampling {
+    use super::*;
 
-  #[wasm_bindgen(getter)]
-  pub fn message(&self) -> String {
-    self._message.clone()
-  }
+    // Mean absolute error of the pitches detected in a 55Hz tone (A1).
+    fn frequency_error(downsample_factor: usize) -> f32 {
+      let mut params = make_test_params(2048);
+      params.set_downsample_factor(downsample_factor);
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
 
-  #[wasm_bindgen(getter)]
-  pub fn pitches(&self) -> js_sys::Array {
-    self
-      ._pitches
-      .clone()
-      .into_iter()
-      .map(JsValue::from)
-      .collect()
-  }
-}
+      detector.set_audio_samples(0, test_utils::sin_signal(55.0, 48000, 48000));
+      let pitches = detector.pitches_vec();
+      assert!(!pitches.is_empty());
 
-#[wasm_bindgen]
-impl PitchDetector {
-  pub fn new(detector_type: String, params: Params) -> PitchDetector {
-    if params.window > MAX_WINDOW_SIZE {
-      panic!(format!(
-        "PitchDetector::new() window size exceeded maximum window size {}",
-        MAX_WINDOW_SIZE
-      ))
+      pitches
+        .iter()
+        .map(|pitch| (pitch.frequency - 55.0).abs())
+        .sum::<f32>()
+        / pitches.len() as f32
     }
 
-    PitchDetector {
-      time_of_first_sample: 0,
-      time_of_next_unprocessed_sample: 0,
-      current_pitch: None,
-      audio_samples: vec![],
+    #[test]
+    fn improves_low_pitch_accuracy() {
+      let error = frequency_error(4);
 
-      params,
+      assert!(error < 0.5, "error {}", error);
+      assert!(error < frequency_error(2));
+    }
 
-      detector: make_detector(detector_type, params),
-      history: None,
+    #[test]
+    fn window_spans_more_samples() {
+      let mut params = make_test_params(1024);
+      params.set_downsample_factor(4);
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+
+      let result = detector.set_audio_samples(0, vec![0.0; 2048]);
+      assert_eq!(result.code(), "not_enough_samples");
     }
-  }
 
-  pub fn set_audio_samples(&mut self, time_of_first_sample: usize, audio_samples: Vec<f32>) {
-    // console_log!("audio_samples.len() {}", audio_samples.len());
+    #[test]
+    fn rejects_span_over_maximum_window() {
+      let mut params = make_test_params(4096);
+      params.set_downsample_factor(2);
+      assert!(check_params(&params).is_ok());
 
-    if audio_samples.len() < self.params.window {
-      panic!(
-        "pitches() insufficient audio samples to analyze. Got {}, need: {} samples",
-        audio_samples.len(),
-        self.params.window
+      params.set_downsample_factor(4);
+      assert_eq!(
+        check_params(&params).unwrap_err().0,
+        AnalysisError::WindowTooLarge
       );
     }
 
-    self.time_of_first_sample = time_of_first_sample;
+    #[test]
+    fn rejects_zero_factor() {
+      let mut params = make_test_params(1024);
+      params.set_downsample_factor(0);
 
-    if time_of_first_sample > self.time_of_next_unprocessed_sample {
-      self.time_of_next_unprocessed_sample = time_of_first_sample;
+      assert_eq!(
+        check_params(&params).unwrap_err().0,
+        AnalysisError::InvalidDownsampleFactor
+      );
     }
-
-    self.audio_samples = audio_samples;
   }
 
-  pub fn index_of_next_unprocessed_sample(&self) -> usize {
-    self.time_of_next_unprocessed_sample - self.time_of_first_sample
-  }
+  mod frames {
+    use super::*;
 
-  pub fn num_audio_samples(&self) -> usize {
-    self.audio_samples.len()
-  }
+    #[test]
+    fn covers_silence_with_unpitched_frames() {
+      let mut samples = test_utils::sin_signal(440.0, 9600, 48000);
+      for sample in samples[3200..6400].iter_mut() {
+        *sample = 0.0;
+      }
 
-  fn pitches_vec(&mut self) -> Vec<Pitch> {
-    let mut pitches: Vec<Pitch> = Vec::<Pitch>::new();
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(1024));
+      detector.set_audio_samples(0, samples);
+      let frames = detector.frames_vec();
 
-    if self.audio_samples.len() < self.params.window {
-      return pitches;
-    }
+      // One frame per hop, with none skipped.
+      assert_eq!(frames.len(), (9600 - 1024) / 256);
+      for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.t, (i * 256) as f32 / 48000.0);
+      }
 
-    let num_unprocessed_samples =
-      self.audio_samples.len() - self.index_of_next_unprocessed_sample();
-    let window_samples = self.params.window;
-    if num_unprocessed_samples < window_samples {
-      return pitches;
+      // Windows entirely within the silence have no pitch and no level.
+      let silent: Vec<&AnalysisFrame> = frames
+        .iter()
+        .filter(|frame| frame.t * 48000.0 >= 3200.0 && frame.t * 48000.0 + 1024.0 <= 6400.0)
+        .collect();
+      assert!(!silent.is_empty());
+      for frame in silent {
+        assert_eq!(frame.pitch(), None);
+        assert_eq!(frame.rms, 0.0);
+        assert_eq!(frame.db, SILENCE_DB);
+      }
+
+      assert!(frames[0].pitch().is_some());
+      assert!(frames[0].onset);
+      assert!(frames[frames.len() - 1].pitch().is_some());
+      assert!(!frames[frames.len() - 1].onset);
     }
 
-    let delta: usize = window_samples / 4;
-    let num_windows = (num_unprocessed_samples - window_samples) / delta;
+    #[test]
+    fn pitched_frames_match_pitches() {
+      let samples = test_utils::sin_signal(440.0, 9600, 48000);
 
-    if num_windows == 0 {
-      return pitches;
-    }
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(1024));
+      detector.set_audio_samples(0, samples.clone());
+      let frames = detector.frames_vec();
 
-    // The chunk is our working memory.
-    let mut chunk = vec![0.0; MAX_WINDOW_SIZE];
+      detector.reset();
+      detector.set_audio_samples(0, samples);
+      let pitches = detector.pitches_vec();
 
-    let index_of_next_unprocessed_sample = self.index_of_next_unprocessed_sample();
+      let frame_pitches: Vec<Pitch> = frames.iter().filter_map(|frame| frame.pitch()).collect();
+      assert_eq!(frame_pitches, pitches);
+    }
+  }
 
-    let detector = self.detector.as_mut();
+  mod setting_samples_from_slice {
+    use super::*;
 
-    for i in 0..num_windows {
-      let index: usize = i * delta + index_of_next_unprocessed_sample;
-      fill_chunk(&self.audio_samples, index, window_samples, &mut chunk);
+    #[test]
+    fn matches_set_audio_samples() {
+      let signal = test_utils::sin_signal(440.0, 9600, 48000);
 
-      let optional_pitch = detector.get_pitch(
-        &chunk[0..window_samples],
-        self.params.sample_rate,
-        self.params.power_threshold,
-        self.params.clarity_threshold,
-        self.history,
-      );
+      let mut from_vec = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      from_vec.set_audio_samples(0, signal.clone());
 
-      // Update next unprocessed sample.
-      self.time_of_next_unprocessed_sample += delta;
+      let mut from_slice = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      from_slice.set_audio_samples_from_slice(0, &signal);
 
-      match optional_pitch {
-        Some(pitch) => {
-          // We detected a pitch.
-          let onset = match self.current_pitch {
-            Some(_current_pitch) => false,
-            None => true,
-          };
+      assert_eq!(from_slice.pitches_vec(), from_vec.pitches_vec());
+    }
 
-          self.current_pitch = Some(pitch.frequency);
+    #[test]
+    fn reuses_buffer_across_calls() {
+      let signal = test_utils::sin_signal(440.0, 4096, 48000);
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
 
-          let sample_time = (self.time_of_next_unprocessed_sample + index) as f32;
+      detector.set_audio_samples_from_slice(0, &signal);
+      let capacity = detector.audio_samples.capacity();
+      let buffer = detector.audio_samples.as_ptr();
 
-          pitches.push(Pitch {
-            clarity: pitch.clarity,
-            frequency: pitch.frequency,
-            t: sample_time / (self.params.sample_rate as f32),
-            onset: onset,
-          })
-        }
-        None => {
-          // A break in the sound or sound quality has occurred. Next resumption will be onset
-          // of a new note.
-          self.current_pitch = None;
+      for frame in 1..60 {
+        detector.set_audio_samples_from_slice(frame * 128, &signal);
 
-          println!(
-            "no pitch calculated in window {}, t: {}, delta_t: {}, window: {}",
-            i,
-            self.time_of_next_unprocessed_sample + index,
-            delta,
-            window_samples
-          );
-        }
+        assert_eq!(detector.audio_samples.capacity(), capacity);
+        assert_eq!(detector.audio_samples.as_ptr(), buffer);
       }
     }
 
-    pitches
-  }
+    #[test]
+    fn errors_on_insufficient_samples() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
 
-  pub fn pitches(&mut self) -> PitchesResult {
-    if self.audio_samples.len() < self.params.window {
-      return PitchesResult::from_error(String::from("not_enough_samples"),
-        String::from(format!("pitches() requires at least {} samples and there are currently {}. Ensure set_audio_samples() has been called once enough samples are available.", self.params.window, self.audio_samples.len()))
-    );
-    }
+      let result = detector.set_audio_samples_from_slice(0, &[0.0; 1024]);
 
-    PitchesResult::from_vec(self.pitches_vec())
+      assert_eq!(result.code(), "not_enough_samples");
+      assert_eq!(
+        result.message(),
+        "set_audio_samples_from_slice() insufficient audio samples to analyze. Got 1024, need: 2048 samples"
+      );
+    }
   }
-}
 
-#[cfg(test)]
-use super::test_utils;
+  mod pending_windows {
+    use super::*;
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+    #[test]
+    fn matches_pitches_of_continuous_tone() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 9600, 48000));
 
-  fn make_test_params(window: usize) -> Params {
-    Params {
-      window,
-      sample_rate: 48000,
-      padding: window / 2,
-      power_threshold: 0.25,
-      clarity_threshold: 0.6,
+      let pending = detector.pending_window_count();
+
+      assert_eq!(pending, (9600 - 2048) / 512);
+      assert_eq!(detector.pitches_vec().len(), pending);
+      assert_eq!(detector.pending_window_count(), 0);
+    }
+
+    #[test]
+    fn none_without_a_window_of_samples() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      assert_eq!(detector.pending_window_count(), 0);
+
+      detector.set_audio_samples(0, vec![0.0; 2048]);
+      assert_eq!(detector.pending_window_count(), 0);
     }
   }
-  mod adding_samples {
+
+  mod confidence {
     use super::*;
 
+    fn confidences(params: &Params) -> Vec<f32> {
+      [0.0, 0.5, 0.8, 0.9, 0.98, 1.0]
+        .iter()
+        .map(|clarity| params.confidence(*clarity))
+        .collect()
+    }
+
     #[test]
-    #[should_panic(expected = "pitches() insufficient audio samples to analyze")]
-    fn panics_on_insufficient_samples() {
-      PitchDetector::new(String::from("McLeod"), make_test_params(2)).set_audio_samples(0, vec![]);
+    fn equals_clarity_by_default() {
+      let params = make_test_params(2048);
+
+      assert_eq!(confidences(&params), vec![0.0, 0.5, 0.8, 0.9, 0.98, 1.0]);
+    }
+
+    #[test]
+    fn maps_clarities_monotonically() {
+      let mut params = make_test_params(2048);
+      params.set_confidence_floor(0.5);
+      params.set_confidence_gamma(2.0);
+
+      let mapped = confidences(&params);
+
+      assert_eq!(mapped[0], 0.0);
+      assert_eq!(mapped[1], 0.0);
+      assert!((mapped[2] - 0.36).abs() < 1e-5);
+      assert!(mapped.windows(2).all(|pair| pair[0] <= pair[1]));
+      assert_eq!(mapped[5], 1.0);
+    }
+
+    #[test]
+    fn detected_pitches_have_mapped_confidence() {
+      let mut params = make_test_params(2048);
+      params.set_confidence_floor(0.5);
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4800, 48000));
+      let pitches = detector.pitches_vec();
+
+      assert!(!pitches.is_empty());
+      for pitch in pitches {
+        assert_eq!(pitch.confidence, params.confidence(pitch.clarity));
+        assert!(pitch.confidence < pitch.clarity);
+      }
     }
   }
 
-  mod detecting_pitches {
+  mod signal_to_noise {
     use super::*;
 
-    const WINDOW: usize = 2048;
+    fn snr_of(signal: Vec<f32>) -> Vec<f32> {
+      let mut params = make_test_params(2048);
+      params.set_clarity_threshold(0.0);
 
-    fn sin_signal_samples(freq_hz: f32, duration_secs: f32) -> Vec<f32> {
-      const SAMPLE_RATE: usize = 48000;
-      let samples: usize = (SAMPLE_RATE as f32 * duration_secs) as usize;
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.set_audio_samples(0, signal);
+      detector.pitches_vec().iter().map(|p| p.snr_db).collect()
+    }
 
-      test_utils::sin_signal(freq_hz, samples, SAMPLE_RATE)
+    #[test]
+    fn clean_sine_has_high_snr() {
+      let snrs = snr_of(test_utils::sin_signal(440.0, 4800, 48000));
+
+      assert!(!snrs.is_empty());
+      assert!(snrs.iter().all(|snr| *snr > 40.0), "snrs {:?}", snrs);
     }
 
     #[test]
-    #[should_panic(expected = "unsupported detector type Not a real pitch detector type")]
-    fn panics_on_missing_detector_type() {
-      PitchDetector::new(
-        String::from("Not a real pitch detector type"),
-        make_test_params(4),
-      );
+    fn noisy_sine_has_low_snr() {
+      let snrs = snr_of(test_utils::sin_signal_with_noise(
+        440.0, 4800, 48000, 0.0, 3,
+      ));
+
+      assert!(!snrs.is_empty());
+      assert!(snrs.iter().all(|snr| *snr < 10.0), "snrs {:?}", snrs);
     }
 
     #[test]
-    fn detects_pitch_autocorrelation() {
-      let mut detector =
-        PitchDetector::new(String::from("Autocorrelation"), make_test_params(WINDOW));
+    fn no_noise_is_max_snr() {
+      let samples = vec![1.0; 256];
 
-      detector.set_audio_samples(0, sin_signal_samples(440.0, 0.1));
-      let pitches = detector.pitches_vec();
+      assert_eq!(harmonic_snr_db(&samples, 48000.0, 480.0), MAX_SNR_DB);
+    }
+  }
+
+  mod priming {
+    use super::*;
+
+    fn onsets_after_priming(priming: &[f32]) -> usize {
+      let signal = test_utils::sin_signal(220.0, 9600, 48000);
+      let mut params = make_test_params(2048);
+      params.set_octave_correction(true);
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), params);
+      detector.prime(priming);
+      detector.set_audio_samples(4800, signal[4800..].to_vec());
 
-      assert_eq!(format!("{:?}", pitches), "[Pitch { t: 0.010666667, frequency: 440.36697, clarity: 0.94680345, onset: true }, Pitch { t: 0.032, frequency: 440.36697, clarity: 0.94702, onset: false }, Pitch { t: 0.053333335, frequency: 440.36697, clarity: 0.9463327, onset: false }, Pitch { t: 0.074666664, frequency: 440.36697, clarity: 0.9471525, onset: false }, Pitch { t: 0.096, frequency: 440.36697, clarity: 0.9465997, onset: false }]");
+      detector.pitches_vec().iter().filter(|p| p.onset).count()
     }
 
     #[test]
-    fn detects_pitch_mcleod() {
-      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+    fn primed_detector_continues_sounding_note() {
+      let signal = test_utils::sin_signal(220.0, 4800, 48000);
 
-      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
-      let pitches = detector.pitches_vec();
+      assert_eq!(onsets_after_priming(&[]), 1);
+      assert_eq!(onsets_after_priming(&signal), 0);
+    }
+
+    #[test]
+    fn priming_leaves_buffer_and_results_untouched() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(48000, vec![0.0; 4096]);
 
-      assert_eq!(format!("{:?}", pitches), "[Pitch { t: 0.010666667, frequency: 220.29074, clarity: 0.894376, onset: true }, Pitch { t: 0.032, frequency: 221.12888, clarity: 0.89288074, onset: false }, Pitch { t: 0.053333335, frequency: 220.72627, clarity: 0.89353347, onset: false }, Pitch { t: 0.074666664, frequency: 220.17342, clarity: 0.8946273, onset: false }, Pitch { t: 0.096, frequency: 220.95581, clarity: 0.89314663, onset: false }]");
+      let mut priming = test_utils::new_real_buffer(2560);
+      priming.extend(test_utils::sin_signal(220.0, 4800, 48000));
+      detector.prime(&priming);
+
+      assert_eq!(detector.num_audio_samples(), 4096);
+      assert_eq!(detector.time_of_first_sample, 48000);
+      assert_eq!(detector.time_of_next_unprocessed_sample, 48000);
+      assert_eq!(detector.latency_samples(), None);
+      assert!(detector.has_current_pitch());
+
+      let result = detector.pitches();
+      assert!(result._pitches.is_empty());
+      assert!(result._rests.is_empty());
     }
 
     #[test]
-    fn returns_only_new_pitches() {
+    fn primes_detector_after_onset() {
+      let mut signal = test_utils::new_real_buffer(24000);
+      signal.extend(test_utils::sin_signal(220.0, 9600, 48000));
       let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal);
+      assert_eq!(detector.pitches_vec().iter().filter(|p| p.onset).count(), 1);
+      let time_of_last_onset = detector.time_of_last_onset;
 
-      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
+      detector.prime(&test_utils::sin_signal(220.0, 9600, 48000));
 
-      // Get the available pitches.
-      /*let initial_pitches = */
-      detector.pitches_vec();
-      // println!("{:?}", initial_pitches);
+      assert_eq!(detector.time_of_last_onset, time_of_last_onset);
+    }
+  }
 
-      println!(
-        "detector.index_of_next_unprocessed_sample {}",
-        detector.index_of_next_unprocessed_sample()
-      );
+  mod fallback_detector {
+    use super::*;
 
-      // Call again. There should be no more to return.
+    // A detector that never finds a pitch itself, loaded with five windows of a 440Hz tone.
+    fn detector_missing_every_pitch() -> PitchDetector {
+      let mut detector = make_scripted_detector(make_test_params(2048), vec![None; 5]);
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4608, 48000));
+      detector
+    }
+
+    #[test]
+    fn fallback_finds_pitch_primary_misses() {
+      let mut detector = detector_missing_every_pitch();
+      assert!(detector.pitches_vec().is_empty());
+
+      let mut detector = detector_missing_every_pitch();
+      detector.set_fallback_detector_type(String::from("Autocorrelation"));
       let pitches = detector.pitches_vec();
-      assert_eq!(pitches.len(), 0);
 
-      detector.set_audio_samples(
-        detector.time_of_next_unprocessed_sample,
-        sin_signal_samples(220.0, 0.1),
+      assert_eq!(
+        detector.fallback_detector_type(),
+        Some(String::from("Autocorrelation"))
       );
-      let more_pitches = detector.pitches_vec();
-      assert_eq!(more_pitches.len(), 5);
+      assert_eq!(pitches.len(), 5);
+      assert!(pitches.iter().all(|p| (p.frequency - 440.0).abs() < 5.0));
+      assert!(pitches[0].onset);
     }
 
     #[test]
-    fn first_pitch_is_an_onset() {
-      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+    fn primary_pitch_is_kept() {
+      let mut detector = make_scripted_detector(make_test_params(2048), vec![Some(220.0); 5]);
+      detector.set_audio_samples(0, test_utils::sin_signal(440.0, 4608, 48000));
+      detector.set_fallback_detector_type(String::from("McLeod"));
 
-      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
-      let pitches = detector.pitches_vec();
+      assert!(detector.pitches_vec().iter().all(|p| p.frequency == 220.0));
+    }
 
-      assert_eq!(pitches[0].onset, true);
+    #[test]
+    fn clearing_removes_fallback() {
+      let mut detector = detector_missing_every_pitch();
+      detector.set_fallback_detector_type(String::from("McLeod"));
+      detector.clear_fallback_detector_type();
+
+      assert_eq!(detector.fallback_detector_type(), None);
+      assert!(detector.pitches_vec().is_empty());
     }
 
     #[test]
-    fn second_pitch_is_not_an_onset() {
-      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+    #[should_panic(expected = "unsupported detector type Unknown")]
+    fn panics_on_unsupported_fallback() {
+      PitchDetector::new(String::from("McLeod"), make_test_params(2048))
+        .set_fallback_detector_type(String::from("Unknown"));
+    }
+  }
 
-      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
-      let pitches = detector.pitches_vec();
+  mod finalized_pitches {
+    use super::*;
 
-      assert_eq!(pitches[1].onset, false);
+    #[test]
+    fn withholds_last_window_until_more_samples_arrive() {
+      let signal = test_utils::sin_signal(440.0, 6656, 48000);
+      let all_times: Vec<f32> = {
+        let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+        detector.set_audio_samples(0, signal[0..6144].to_vec());
+        detector.pitches_vec().iter().map(|p| p.t).collect()
+      };
+
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.set_audio_samples(0, signal[0..6144].to_vec());
+      let finalized: Vec<f32> = detector
+        .finalized_pitches()
+        ._pitches
+        .iter()
+        .map(|p| p.t)
+        .collect();
+
+      assert_eq!(all_times.len(), 8);
+      assert_eq!(finalized, all_times[0..4]);
+
+      detector.push_samples(&signal[6144..]);
+      let next = detector.finalized_pitches();
+
+      assert_eq!(next._pitches.len(), 1);
+      assert_eq!(next._pitches[0].t, all_times[4]);
     }
 
     #[test]
-    fn first_pitch_after_silence_is_an_onset() {
-      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(WINDOW));
+    fn errors_on_insufficient_samples() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.push_samples(&[0.0; 1024]);
 
-      // Get first round of pitches.
-      detector.set_audio_samples(0, sin_signal_samples(220.0, 0.1));
-      detector.pitches_vec();
+      assert_eq!(detector.finalized_pitches().code(), "not_enough_samples");
+    }
+  }
 
-      // Add a some flat signal / noise where no pitches are generated.
-      detector.set_audio_samples(
-        detector.time_of_next_unprocessed_sample,
-        sin_signal_samples(0.0, 0.1),
-      );
-      detector.pitches_vec();
+  mod previewing {
+    use super::*;
 
-      // Resumption of a signal that produces pitches.
-      detector.set_audio_samples(
-        detector.time_of_next_unprocessed_sample,
-        sin_signal_samples(440.0, 0.1),
-      );
+    #[test]
+    fn previews_pitch_of_half_a_window() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      detector.push_samples(&test_utils::sin_signal(440.0, 1024, 48000));
+
+      let pitch = detector.preview_pitch().unwrap();
+
+      assert!(pitch.preview);
+      assert!(!pitch.onset);
+      assert_eq!(pitch.midi_note, 69);
+      assert!((pitch.frequency - 440.0).abs() < 5.0);
+      assert!(pitch.clarity <= 0.5);
+      assert!(pitch.to_string().ends_with(", preview)"));
+    }
+
+    #[test]
+    fn does_not_affect_later_detection() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      let samples = test_utils::sin_signal(440.0, 4096, 48000);
+      detector.push_samples(&samples[0..1024]);
+      detector.preview_pitch();
+
+      detector.push_samples(&samples[1024..]);
       let pitches = detector.pitches_vec();
 
-      assert_eq!(pitches[0].onset, true);
+      assert!(pitches[0].onset);
+      assert!(pitches.iter().all(|pitch| !pitch.preview));
+    }
+
+    #[test]
+    fn none_once_a_window_is_available() {
+      let mut detector = PitchDetector::new(String::from("McLeod"), make_test_params(2048));
+      assert_eq!(detector.preview_pitch(), None);
+
+      detector.push_samples(&test_utils::sin_signal(440.0, 2048, 48000));
+      assert_eq!(detector.preview_pitch(), None);
     }
   }
 }
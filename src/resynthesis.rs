@@ -0,0 +1,124 @@
+// Audio regenerated from detected pitches, e.g. to hear what the detector heard or to preview
+// pitch correction.
+
+use super::pitch_detector::Pitch;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Waveform {
+  Sine,
+  // Brighter, with every harmonic of the pitch.
+  Sawtooth,
+}
+
+fn oscillator(waveform: Waveform, phase: f32) -> f32 {
+  match waveform {
+    Waveform::Sine => (2.0 * std::f32::consts::PI * phase).sin(),
+    Waveform::Sawtooth => 2.0 * phase.fract() - 1.0,
+  }
+}
+
+// Overlap-adds a Hann windowed tone at each pitch's frequency, window samples long from the
+// pitch's time, into a buffer whose sample i is at i / sample_rate seconds. Tones are in phase
+// with each other so steady notes don't cancel where windows overlap, and overlaps are scaled
+// back to unit amplitude.
+pub fn resynthesize(
+  pitches: &[Pitch],
+  sample_rate: usize,
+  window: usize,
+  waveform: Waveform,
+) -> Vec<f32> {
+  let start_of = |pitch: &Pitch| (pitch.t * sample_rate as f32).round() as usize;
+
+  let len = pitches
+    .iter()
+    .map(|pitch| start_of(pitch) + window)
+    .max()
+    .unwrap_or(0);
+  let mut output = vec![0.0; len];
+  let mut weights = vec![0.0; len];
+
+  let dx = 2.0 * std::f32::consts::PI / (window.max(2) - 1) as f32;
+
+  for pitch in pitches {
+    let start = start_of(pitch);
+
+    for i in 0..window {
+      let n = start + i;
+      let weight = 0.5 - 0.5 * (i as f32 * dx).cos();
+      // Phase from the absolute sample index, in cycles, wrapped to keep precision.
+      let phase = (n as f64 * pitch.frequency as f64 / sample_rate as f64).fract() as f32;
+
+      output[n] += weight * oscillator(waveform, phase);
+      weights[n] += weight;
+    }
+  }
+
+  for (sample, weight) in output.iter_mut().zip(weights.iter()) {
+    *sample /= weight.max(1.0);
+  }
+
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pitch_detector::{make_params, PitchDetector};
+  use crate::test_utils;
+
+  const SAMPLE_RATE: usize = 48000;
+  const WINDOW: usize = 2048;
+
+  fn detect(signal: Vec<f32>) -> Vec<Pitch> {
+    let mut detector = PitchDetector::new(
+      String::from("McLeod"),
+      make_params(WINDOW, SAMPLE_RATE, 0.25, 0.6),
+    );
+    detector.set_audio_samples(0, signal);
+    detector.pitches_vec()
+  }
+
+  fn assert_detected_near(signal: Vec<f32>, frequency: f32) {
+    let pitches = detect(signal);
+
+    assert!(!pitches.is_empty());
+    for pitch in pitches {
+      assert!(
+        (pitch.frequency - frequency).abs() < 2.0,
+        "detected {}",
+        pitch
+      );
+    }
+  }
+
+  #[test]
+  fn resynthesized_tone_has_detected_pitch() {
+    let pitches = detect(test_utils::sin_signal(220.0, 9600, SAMPLE_RATE));
+
+    let output = resynthesize(&pitches, SAMPLE_RATE, WINDOW, Waveform::Sine);
+
+    assert_eq!(
+      output.len(),
+      (pitches[pitches.len() - 1].t * 48000.0).round() as usize + WINDOW
+    );
+    assert!(output.iter().all(|sample| sample.abs() <= 1.0));
+    assert_detected_near(output, 220.0);
+  }
+
+  #[test]
+  fn sawtooth_has_same_pitch() {
+    let pitches = detect(test_utils::sin_signal(220.0, 9600, SAMPLE_RATE));
+
+    assert_detected_near(
+      resynthesize(&pitches, SAMPLE_RATE, WINDOW, Waveform::Sawtooth),
+      220.0,
+    );
+  }
+
+  #[test]
+  fn empty_for_no_pitches() {
+    assert!(resynthesize(&[], SAMPLE_RATE, WINDOW, Waveform::Sine).is_empty());
+  }
+}
@@ -0,0 +1,118 @@
+// Discrete notes formed from sequences of detected pitches, e.g. for transcribing a melody.
+
+use super::pitch_detector::Pitch;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+  pub midi: u8,
+  // Times in seconds of the first and last pitches of the note.
+  pub start_t: f32,
+  pub end_t: f32,
+  pub mean_frequency: f32,
+}
+
+fn note_from(pitches: &[Pitch]) -> Note {
+  Note {
+    midi: pitches[0].midi_note,
+    start_t: pitches[0].t,
+    end_t: pitches[pitches.len() - 1].t,
+    mean_frequency: pitches.iter().map(|p| p.frequency).sum::<f32>() / pitches.len() as f32,
+  }
+}
+
+// Groups consecutive pitches (in time order) into notes, starting a new note at each onset and
+// whenever the nearest MIDI note changes.
+pub fn segment_notes(pitches: &[Pitch]) -> Vec<Note> {
+  let mut notes = vec![];
+  let mut note_start = 0;
+
+  for i in 1..pitches.len() {
+    if pitches[i].onset || pitches[i].midi_note != pitches[i - 1].midi_note {
+      notes.push(note_from(&pitches[note_start..i]));
+      note_start = i;
+    }
+  }
+
+  if !pitches.is_empty() {
+    notes.push(note_from(&pitches[note_start..]));
+  }
+
+  notes
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pitch_detector::{make_params, PitchDetector};
+  use crate::test_utils;
+
+  fn pitch_at(t: f32, midi_note: u8, onset: bool) -> Pitch {
+    Pitch {
+      t,
+      frequency: 440.0,
+      midi_note,
+      onset,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn segments_detected_notes() {
+    let mut signal = test_utils::sin_signal(220.0, 9600, 48000);
+    signal.extend(test_utils::new_real_buffer(4800));
+    signal.extend(test_utils::sin_signal(330.0, 9600, 48000));
+
+    let mut detector =
+      PitchDetector::new(String::from("McLeod"), make_params(2048, 48000, 0.25, 0.6));
+    detector.set_audio_samples(0, signal);
+    let notes = segment_notes(&detector.pitches_vec());
+
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].midi, 57);
+    assert_eq!(notes[1].midi, 64);
+    assert_eq!(notes[0].start_t, 0.0);
+    assert!(notes[0].end_t < 0.2);
+    assert!(notes[1].start_t > notes[0].end_t);
+    assert!((notes[0].mean_frequency - 220.0).abs() < 2.0);
+    assert!((notes[1].mean_frequency - 330.0).abs() < 2.0);
+  }
+
+  #[test]
+  fn splits_at_note_change_without_onset() {
+    let notes = segment_notes(&[
+      pitch_at(0.0, 69, true),
+      pitch_at(0.1, 69, false),
+      pitch_at(0.2, 71, false),
+      pitch_at(0.3, 71, false),
+    ]);
+
+    assert_eq!(notes.len(), 2);
+    assert_eq!(
+      (notes[0].midi, notes[0].start_t, notes[0].end_t),
+      (69, 0.0, 0.1)
+    );
+    assert_eq!(
+      (notes[1].midi, notes[1].start_t, notes[1].end_t),
+      (71, 0.2, 0.3)
+    );
+  }
+
+  #[test]
+  fn splits_repeated_note_at_onset() {
+    let notes = segment_notes(&[
+      pitch_at(0.0, 69, true),
+      pitch_at(0.1, 69, false),
+      pitch_at(0.2, 69, true),
+    ]);
+
+    assert_eq!(notes.len(), 2);
+  }
+
+  #[test]
+  fn no_notes_without_pitches() {
+    assert!(segment_notes(&[]).is_empty());
+  }
+}
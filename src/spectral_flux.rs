@@ -0,0 +1,123 @@
+// Onsets found from sudden increases in the spectrum, independent of pitch, so they work for
+// chords and other polyphonic material the pitch detectors can't follow.
+
+use super::fft_detector::FftDetector;
+use wasm_bindgen::prelude::*;
+
+// Flux must reach this fraction of the largest flux in the recording to count as an onset.
+const FLUX_THRESHOLD_RATIO: f32 = 0.3;
+
+// Onsets closer together than this are treated as one attack.
+const MIN_ONSET_GAP_MS: f32 = 50.0;
+
+// Spectral flux of each window: the total increase in magnitude over all bins since the previous
+// window (silence before the first).
+fn spectral_flux(samples: &[f32], window: usize, hop: usize) -> Vec<f32> {
+  let mut fft = FftDetector::new(window, 0);
+  let mut previous = vec![0.0; window / 2];
+
+  (0..)
+    .map(|i| i * hop)
+    .take_while(|start| start + window <= samples.len())
+    .map(|start| {
+      let spectrum = fft.magnitude_spectrum(&samples[start..start + window]);
+      let flux = spectrum
+        .iter()
+        .zip(previous.iter())
+        .map(|(current, previous)| (current - previous).max(0.0))
+        .sum();
+
+      previous = spectrum;
+      flux
+    })
+    .collect()
+}
+
+// Sample indices (of window centres) where the spectral flux peaks, for rhythm analysis of
+// material with several notes at once. A peak must reach a fraction of the largest flux, and
+// follow the previous onset by at least MIN_ONSET_GAP_MS. The window needs to be long enough to
+// resolve neighbouring notes (e.g. 2048 at 48kHz), otherwise beating between them reads as
+// onsets.
+#[wasm_bindgen]
+pub fn detect_onsets(samples: &[f32], window: usize, hop: usize, sample_rate: usize) -> Vec<usize> {
+  if window == 0 || hop == 0 {
+    return vec![];
+  }
+
+  let flux = spectral_flux(samples, window, hop);
+  let threshold = FLUX_THRESHOLD_RATIO * flux.iter().cloned().fold(0.0, f32::max);
+  let min_gap = (MIN_ONSET_GAP_MS * sample_rate as f32 / 1000.0) as usize;
+
+  let mut onsets: Vec<usize> = vec![];
+  for i in 0..flux.len() {
+    let is_peak = flux[i] > 0.0
+      && flux[i] >= threshold
+      && (i == 0 || flux[i] > flux[i - 1])
+      && (i + 1 == flux.len() || flux[i] >= flux[i + 1]);
+    if !is_peak {
+      continue;
+    }
+
+    let onset = i * hop + window / 2;
+    let too_close = match onsets.last() {
+      Some(last) => onset - last < min_gap,
+      None => false,
+    };
+    if too_close {
+      continue;
+    }
+
+    onsets.push(onset);
+  }
+
+  onsets
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils;
+
+  const SAMPLE_RATE: usize = 48000;
+  const WINDOW: usize = 2048;
+  const HOP: usize = 256;
+
+  // Equal mix of sines at the frequencies.
+  fn chord(frequencies: &[f32], size: usize) -> Vec<f32> {
+    let mut signal = test_utils::new_real_buffer(size);
+    for frequency in frequencies {
+      let note = test_utils::sin_signal(*frequency, size, SAMPLE_RATE);
+      for (sample, note_sample) in signal.iter_mut().zip(note.iter()) {
+        *sample += note_sample / frequencies.len() as f32;
+      }
+    }
+    signal
+  }
+
+  #[test]
+  fn finds_two_chord_attacks() {
+    let mut signal = test_utils::new_real_buffer(9600);
+    signal.extend(chord(&[261.63, 329.63, 392.0], 24000));
+    signal.extend(chord(&[293.66, 349.23, 440.0], 24000));
+
+    let onsets = detect_onsets(&signal, WINDOW, HOP, SAMPLE_RATE);
+
+    assert_eq!(onsets.len(), 2, "onsets {:?}", onsets);
+    assert!(
+      (onsets[0] as i32 - 9600).abs() < WINDOW as i32 / 2,
+      "onsets {:?}",
+      onsets
+    );
+    assert!(
+      (onsets[1] as i32 - 33600).abs() < WINDOW as i32 / 2,
+      "onsets {:?}",
+      onsets
+    );
+  }
+
+  #[test]
+  fn no_onsets_in_silence() {
+    assert!(detect_onsets(&test_utils::new_real_buffer(9600), WINDOW, HOP, SAMPLE_RATE).is_empty());
+    assert!(detect_onsets(&[], WINDOW, HOP, SAMPLE_RATE).is_empty());
+  }
+}
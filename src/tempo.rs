@@ -0,0 +1,64 @@
+// Tempo estimated from the times at which notes begin.
+
+// Fewer onsets than this give no interval to measure.
+const MIN_ONSETS_FOR_TEMPO: usize = 2;
+
+// Beats per minute from onset times (in seconds, in time order, as from PitchesResult::onsets()),
+// taking the median interval between consecutive onsets as the beat so an occasional missed or
+// extra onset doesn't skew it. Returns None if there are too few onsets.
+pub fn estimate_tempo(onsets: &[f32]) -> Option<f32> {
+  if onsets.len() < MIN_ONSETS_FOR_TEMPO {
+    return None;
+  }
+
+  let mut intervals: Vec<f32> = onsets
+    .windows(2)
+    .map(|pair| pair[1] - pair[0])
+    .filter(|interval| *interval > 0.0)
+    .collect();
+  if intervals.is_empty() {
+    return None;
+  }
+  intervals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+  let mid = intervals.len() / 2;
+  let median = match intervals.len() % 2 {
+    0 => (intervals[mid - 1] + intervals[mid]) / 2.0,
+    _ => intervals[mid],
+  };
+
+  Some(60.0 / median)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn onsets_every(seconds: f32, count: usize) -> Vec<f32> {
+    (0..count).map(|i| i as f32 * seconds).collect()
+  }
+
+  #[test]
+  fn estimates_120_bpm() {
+    let tempo = estimate_tempo(&onsets_every(0.5, 8)).unwrap();
+
+    assert!((tempo - 120.0).abs() < 0.01);
+  }
+
+  #[test]
+  fn ignores_an_extra_onset() {
+    let mut onsets = onsets_every(0.5, 8);
+    onsets.insert(3, onsets[2] + 0.1);
+
+    let tempo = estimate_tempo(&onsets).unwrap();
+
+    assert!((tempo - 120.0).abs() < 0.01);
+  }
+
+  #[test]
+  fn none_for_too_few_onsets() {
+    assert_eq!(estimate_tempo(&[]), None);
+    assert_eq!(estimate_tempo(&[0.5]), None);
+    assert_eq!(estimate_tempo(&[0.5, 0.5]), None);
+  }
+}
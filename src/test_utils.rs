@@ -13,3 +13,126 @@ pub fn sin_signal(freq: f32, size: usize, sample_rate: usize) -> Vec<f32> {
   }
   signal
 }
+
+// Higher precision equivalent of sin_signal() for exercising f64 detectors.
+pub fn sin_signal_f64(freq: f64, size: usize, sample_rate: usize) -> Vec<f64> {
+  let two_pi = 2.0 * std::f64::consts::PI;
+  let dx = two_pi * freq / sample_rate as f64;
+  (0..size).map(|i| (i as f64 * dx).sin()).collect()
+}
+
+// A sine shaped like a played note: rising linearly from silence to full amplitude over attack
+// seconds, falling to the sustain level (0..1) over decay seconds, holding, then falling back to
+// silence over the final release seconds.
+pub fn enveloped_signal(
+  freq: f32,
+  size: usize,
+  sample_rate: usize,
+  attack: f32,
+  decay: f32,
+  sustain: f32,
+  release: f32,
+) -> Vec<f32> {
+  let duration = size as f32 / sample_rate as f32;
+  let release_start = duration - release;
+
+  let level_at = |t: f32| {
+    let level = if t < attack {
+      t / attack
+    } else if t < attack + decay {
+      1.0 - (1.0 - sustain) * (t - attack) / decay
+    } else {
+      sustain
+    };
+
+    if t >= release_start {
+      level * (duration - t) / release
+    } else {
+      level
+    }
+  };
+
+  sin_signal(freq, size, sample_rate)
+    .iter()
+    .enumerate()
+    .map(|(i, sample)| sample * level_at(i as f32 / sample_rate as f32))
+    .collect()
+}
+
+// Sum of sines at integer multiples of the fundamental, harmonic_amplitudes[0] being the
+// fundamental's amplitude, [1] the second harmonic's, and so on.
+pub fn harmonic_signal(
+  fundamental: f32,
+  harmonic_amplitudes: &[f32],
+  size: usize,
+  sample_rate: usize,
+) -> Vec<f32> {
+  let mut signal = new_real_buffer(size);
+  for (i, amplitude) in harmonic_amplitudes.iter().enumerate() {
+    let harmonic = sin_signal(fundamental * (i + 1) as f32, size, sample_rate);
+    for (sample, harmonic_sample) in signal.iter_mut().zip(harmonic.iter()) {
+      *sample += amplitude * harmonic_sample;
+    }
+  }
+  signal
+}
+
+// Rises linearly from -1 to 1 each period, so contains every harmonic of freq.
+pub fn saw_signal(freq: f32, size: usize, sample_rate: usize) -> Vec<f32> {
+  let period = sample_rate as f32 / freq;
+  (0..size)
+    .map(|i| {
+      let phase = (i as f32 / period).fract();
+      2.0 * phase - 1.0
+    })
+    .collect()
+}
+
+// Alternates between 1 and -1 each half period, so contains the odd harmonics of freq.
+pub fn square_signal(freq: f32, size: usize, sample_rate: usize) -> Vec<f32> {
+  let period = sample_rate as f32 / freq;
+  (0..size)
+    .map(|i| {
+      if (i as f32 / period).fract() < 0.5 {
+        1.0
+      } else {
+        -1.0
+      }
+    })
+    .collect()
+}
+
+// Uniform noise in -1..1. The same seed always gives the same samples.
+pub fn white_noise(size: usize, seed: u64) -> Vec<f32> {
+  // xorshift64, which gets stuck at zero so needs a non-zero state.
+  let mut state = seed.max(1);
+  (0..size)
+    .map(|_| {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      (state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    })
+    .collect()
+}
+
+// A sine (amplitude 1) mixed with white_noise() scaled to give the signal-to-noise ratio in
+// decibels. The same seed always gives the same samples.
+pub fn sin_signal_with_noise(
+  freq: f32,
+  size: usize,
+  sample_rate: usize,
+  snr_db: f32,
+  seed: u64,
+) -> Vec<f32> {
+  // RMS amplitudes of a unit sine and of uniform noise in -1..1.
+  let signal_rms = 1.0 / 2.0_f32.sqrt();
+  let noise_rms = 1.0 / 3.0_f32.sqrt();
+  let noise_gain = signal_rms / 10.0_f32.powf(snr_db / 20.0) / noise_rms;
+
+  sin_signal(freq, size, sample_rate)
+    .iter()
+    .zip(white_noise(size, seed).iter())
+    .map(|(s, n)| s + noise_gain * n)
+    .collect()
+}
@@ -1,6 +1,9 @@
+use super::pitch_detector::Pitch;
 use circular_queue::CircularQueue;
+use serde::Serialize;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize)]
+#[serde(transparent)]
 pub struct EventTime {
   pub ms: f32,
 }
@@ -12,9 +15,22 @@ impl EventTime {
       false => None,
     }
   }
+
+  pub fn from_sample_index(index: usize, sample_rate: usize) -> Option<EventTime> {
+    if sample_rate == 0 {
+      return None;
+    }
+
+    EventTime::new(index as f32 * 1000.0 / sample_rate as f32)
+  }
+
+  // Index of the sample nearest to this time.
+  pub fn to_sample_index(&self, sample_rate: usize) -> usize {
+    (self.ms * sample_rate as f32 / 1000.0).round() as usize
+  }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize)]
 pub struct SeriesEvent {
   pub time_from_start_ms: EventTime,
   pub pitch_hz: f32,
@@ -29,9 +45,16 @@ impl Series {
   pub fn new(name: String) -> Series {
     const DEFAULT_CAPACITY: usize = 100;
 
+    Series::with_capacity(name, DEFAULT_CAPACITY)
+  }
+
+  // Once full, adding an event discards the oldest one.
+  pub fn with_capacity(name: String, capacity: usize) -> Series {
+    assert!(capacity > 0, "Series capacity must be greater than 0");
+
     Series {
       name,
-      events: CircularQueue::with_capacity(DEFAULT_CAPACITY),
+      events: CircularQueue::with_capacity(capacity),
     }
   }
 
@@ -56,6 +79,22 @@ impl Series {
     });
   }
 
+  // Adds an event for each detected pitch, skipping any that are not later than the most recent
+  // event already in the series.
+  pub fn add_pitches(&mut self, pitches: &[Pitch]) {
+    for pitch in pitches {
+      let time_from_start_ms = pitch.t * 1000.0;
+
+      let in_order = self
+        .time_of_most_recent_event()
+        .map_or(time_from_start_ms >= 0.0, |t| t.ms < time_from_start_ms);
+
+      if in_order {
+        self.add_pitch_event(time_from_start_ms, pitch.frequency);
+      }
+    }
+  }
+
   fn time_of_most_recent_event(&self) -> Option<EventTime> {
     self.events.iter().map(|e| e.time_from_start_ms).next()
   }
@@ -69,6 +108,76 @@ impl Series {
       .cloned()
       .collect();
   }
+
+  // Events at or after start_ms and before end_ms, oldest first.
+  pub fn events_between(&self, start_ms: f32, end_ms: f32) -> Vec<SeriesEvent> {
+    self
+      .events
+      .iter()
+      .filter(|e| e.time_from_start_ms.ms >= start_ms && e.time_from_start_ms.ms < end_ms)
+      .rev()
+      .cloned()
+      .collect()
+  }
+
+  // Lowest, highest and mean pitch over the stored events, e.g. for summarizing the range of a
+  // practice session. None if there are no events.
+  pub fn min_pitch_hz(&self) -> Option<f32> {
+    self
+      .events
+      .iter()
+      .map(|e| e.pitch_hz)
+      .min_by(|a, b| a.partial_cmp(b).unwrap())
+  }
+
+  pub fn max_pitch_hz(&self) -> Option<f32> {
+    self
+      .events
+      .iter()
+      .map(|e| e.pitch_hz)
+      .max_by(|a, b| a.partial_cmp(b).unwrap())
+  }
+
+  pub fn mean_pitch_hz(&self) -> Option<f32> {
+    if self.events.is_empty() {
+      return None;
+    }
+
+    Some(self.events.iter().map(|e| e.pitch_hz).sum::<f32>() / self.events.len() as f32)
+  }
+
+  // Time from the oldest to the most recent stored event. None if there are no events.
+  pub fn time_span_ms(&self) -> Option<f32> {
+    let oldest = self.events.asc_iter().next()?;
+    let most_recent = self.time_of_most_recent_event()?;
+
+    Some(most_recent.ms - oldest.time_from_start_ms.ms)
+  }
+
+  // The series name and all of its events, oldest first.
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(&SeriesJson {
+      name: &self.name,
+      events: self.events.asc_iter().cloned().collect(),
+    })
+    .unwrap()
+  }
+
+  // Like to_json() but with only the events from events_after(), e.g. for periodically sending
+  // just the new events to a server.
+  pub fn events_after_as_json(&self, after_ms: f32) -> String {
+    serde_json::to_string(&SeriesJson {
+      name: &self.name,
+      events: self.events_after(after_ms),
+    })
+    .unwrap()
+  }
+}
+
+#[derive(Serialize)]
+struct SeriesJson<'a> {
+  name: &'a str,
+  events: Vec<SeriesEvent>,
 }
 
 pub struct Timeline {
@@ -83,12 +192,72 @@ impl Timeline {
   pub fn add_series(&mut self, series: Series) {
     self.series.push(series);
   }
+
+  pub fn series_by_name(&self, name: &str) -> Option<&Series> {
+    self.series.iter().find(|s| s.name == name)
+  }
+
+  pub fn series_by_name_mut(&mut self, name: &str) -> Option<&mut Series> {
+    self.series.iter_mut().find(|s| s.name == name)
+  }
+
+  // Returns whether a series with the name was found and removed.
+  pub fn remove_series(&mut self, name: &str) -> bool {
+    let len_before = self.series.len();
+
+    self.series.retain(|s| s.name != name);
+
+    self.series.len() != len_before
+  }
+
+  pub fn clear(&mut self) {
+    self.series.clear();
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  mod event_time {
+    use super::*;
+
+    #[test]
+    fn negative_time_is_invalid() {
+      assert!(EventTime::new(-1.0).is_none());
+    }
+
+    #[test]
+    fn from_sample_index() {
+      assert_eq!(
+        EventTime::from_sample_index(48000, 48000).unwrap().ms,
+        1000.0
+      );
+      assert_eq!(
+        EventTime::from_sample_index(22050, 44100).unwrap().ms,
+        500.0
+      );
+    }
+
+    #[test]
+    fn from_sample_index_with_zero_sample_rate() {
+      assert!(EventTime::from_sample_index(48000, 0).is_none());
+    }
+
+    #[test]
+    fn round_trips_sample_index() {
+      let time = EventTime::from_sample_index(48000, 48000).unwrap();
+
+      assert_eq!(time.to_sample_index(48000), 48000);
+    }
+
+    #[test]
+    fn to_sample_index_rounds_to_nearest() {
+      assert_eq!(EventTime::new(0.01).unwrap().to_sample_index(48000), 0);
+      assert_eq!(EventTime::new(0.02).unwrap().to_sample_index(48000), 1);
+    }
+  }
+
   mod series {
     use super::*;
 
@@ -142,6 +311,72 @@ mod tests {
       assert_eq!(series.events.len(), 100);
     }
 
+    fn pitch_at(t: f32, frequency: f32) -> Pitch {
+      Pitch {
+        t,
+        frequency,
+        ..Default::default()
+      }
+    }
+
+    #[test]
+    fn adding_detected_pitches() {
+      let mut series = Series::new(String::from("Series"));
+
+      series.add_pitches(&[
+        pitch_at(0.0, 220.0),
+        pitch_at(0.5, 440.0),
+        pitch_at(1.0, 880.0),
+      ]);
+
+      let events = series.events_after(0.0);
+      let times: Vec<f32> = events.iter().map(|e| e.time_from_start_ms.ms).collect();
+      let pitches: Vec<f32> = events.iter().map(|e| e.pitch_hz).collect();
+      assert_eq!(times, [0.0, 500.0, 1000.0]);
+      assert_eq!(pitches, [220.0, 440.0, 880.0]);
+    }
+
+    #[test]
+    fn adding_detected_pitches_skips_out_of_order() {
+      let mut series = Series::new(String::from("Series"));
+      series.add_pitch_event(500.0, 220.0);
+
+      series.add_pitches(&[
+        pitch_at(0.25, 440.0),
+        pitch_at(0.5, 440.0),
+        pitch_at(0.75, 880.0),
+      ]);
+
+      let times: Vec<f32> = series
+        .events_after(0.0)
+        .iter()
+        .map(|e| e.time_from_start_ms.ms)
+        .collect();
+      assert_eq!(times, [500.0, 750.0]);
+    }
+
+    #[test]
+    fn series_with_custom_capacity_keeps_most_recent() {
+      let mut series = Series::with_capacity(String::from("Series"), 5);
+
+      for i in 0..10 {
+        series.add_pitch_event(i as f32, 440.0);
+      }
+
+      let times: Vec<f32> = series
+        .events_after(0.0)
+        .iter()
+        .map(|e| e.time_from_start_ms.ms)
+        .collect();
+      assert_eq!(times, [5.0, 6.0, 7.0, 8.0, 9.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Series capacity must be greater than 0")]
+    fn series_with_zero_capacity() {
+      Series::with_capacity(String::from("Series"), 0);
+    }
+
     #[test]
     fn retrieve_all_events_by_time() {
       let mut series = Series::new(String::from("Series"));
@@ -177,6 +412,118 @@ mod tests {
     }
   }
 
+  mod statistics {
+    use super::*;
+
+    #[test]
+    fn summarizes_events() {
+      let mut series = Series::new(String::from("Series"));
+
+      series.add_pitch_event(100.0, 440.0);
+      series.add_pitch_event(200.0, 220.0);
+      series.add_pitch_event(300.0, 880.0);
+      series.add_pitch_event(450.0, 330.0);
+
+      assert_eq!(series.min_pitch_hz(), Some(220.0));
+      assert_eq!(series.max_pitch_hz(), Some(880.0));
+      assert_eq!(series.mean_pitch_hz(), Some(467.5));
+      assert_eq!(series.time_span_ms(), Some(350.0));
+    }
+
+    #[test]
+    fn empty_series_has_no_statistics() {
+      let series = Series::new(String::from("Series"));
+
+      assert_eq!(series.min_pitch_hz(), None);
+      assert_eq!(series.max_pitch_hz(), None);
+      assert_eq!(series.mean_pitch_hz(), None);
+      assert_eq!(series.time_span_ms(), None);
+    }
+  }
+
+  mod events_between {
+    use super::*;
+
+    fn times_between(series: &Series, start_ms: f32, end_ms: f32) -> Vec<f32> {
+      series
+        .events_between(start_ms, end_ms)
+        .iter()
+        .map(|e| e.time_from_start_ms.ms)
+        .collect()
+    }
+
+    #[test]
+    fn retrieve_events_in_range() {
+      let mut series = Series::new(String::from("Series"));
+
+      series.add_pitch_event(1.0, 220.0);
+      series.add_pitch_event(2.0, 440.0);
+      series.add_pitch_event(3.0, 220.0);
+      series.add_pitch_event(4.0, 880.0);
+
+      assert_eq!(times_between(&series, 1.5, 3.5), [2.0, 3.0]);
+    }
+
+    #[test]
+    fn range_includes_start_and_excludes_end() {
+      let mut series = Series::new(String::from("Series"));
+
+      series.add_pitch_event(1.0, 220.0);
+      series.add_pitch_event(2.0, 440.0);
+      series.add_pitch_event(3.0, 220.0);
+
+      assert_eq!(times_between(&series, 1.0, 3.0), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn empty_range() {
+      let mut series = Series::new(String::from("Series"));
+
+      series.add_pitch_event(1.0, 220.0);
+
+      assert!(times_between(&series, 2.0, 2.0).is_empty());
+    }
+  }
+
+  mod json {
+    use super::*;
+
+    #[test]
+    fn series_to_json_oldest_first() {
+      let mut series = Series::new(String::from("Detected"));
+
+      series.add_pitch_event(0.0, 220.0);
+      series.add_pitch_event(2.0, 440.0);
+      series.add_pitch_event(3.5, 880.0);
+
+      assert_eq!(
+        series.to_json(),
+        r#"{"name":"Detected","events":[{"time_from_start_ms":0.0,"pitch_hz":220.0},{"time_from_start_ms":2.0,"pitch_hz":440.0},{"time_from_start_ms":3.5,"pitch_hz":880.0}]}"#
+      );
+    }
+
+    #[test]
+    fn events_after_to_json() {
+      let mut series = Series::new(String::from("Detected"));
+
+      series.add_pitch_event(0.0, 220.0);
+      series.add_pitch_event(2.0, 440.0);
+      series.add_pitch_event(4.0, 880.0);
+
+      assert_eq!(
+        series.events_after_as_json(1.0),
+        r#"{"name":"Detected","events":[{"time_from_start_ms":2.0,"pitch_hz":440.0},{"time_from_start_ms":4.0,"pitch_hz":880.0}]}"#
+      );
+    }
+
+    #[test]
+    fn empty_series_to_json() {
+      let series = Series::new(String::from("Empty"));
+
+      assert_eq!(series.to_json(), r#"{"name":"Empty","events":[]}"#);
+    }
+  }
+
   mod timeline {
     use super::*;
 
@@ -196,5 +543,78 @@ mod tests {
 
       assert_eq!(timeline.series.len(), 2);
     }
+
+    #[test]
+    fn finding_series_by_name() {
+      let mut timeline = Timeline::new();
+
+      timeline.add_series(Series::new(String::from("detected")));
+      timeline.add_series(Series::new(String::from("reference")));
+
+      assert_eq!(
+        timeline.series_by_name("detected").unwrap().name,
+        "detected"
+      );
+      assert_eq!(
+        timeline.series_by_name("reference").unwrap().name,
+        "reference"
+      );
+      assert!(timeline.series_by_name("unknown").is_none());
+    }
+
+    #[test]
+    fn adding_events_to_series_found_by_name() {
+      let mut timeline = Timeline::new();
+
+      timeline.add_series(Series::new(String::from("detected")));
+      timeline.add_series(Series::new(String::from("reference")));
+
+      timeline
+        .series_by_name_mut("reference")
+        .unwrap()
+        .add_pitch_event(0.0, 440.0);
+
+      assert_eq!(timeline.series_by_name("detected").unwrap().events.len(), 0);
+      assert_eq!(
+        timeline.series_by_name("reference").unwrap().events.len(),
+        1
+      );
+      assert!(timeline.series_by_name_mut("unknown").is_none());
+    }
+
+    #[test]
+    fn removing_existing_series() {
+      let mut timeline = Timeline::new();
+
+      timeline.add_series(Series::new(String::from("Series A")));
+      timeline.add_series(Series::new(String::from("Series B")));
+
+      assert!(timeline.remove_series("Series A"));
+      assert_eq!(timeline.series.len(), 1);
+      assert!(timeline.series_by_name("Series A").is_none());
+      assert!(timeline.series_by_name("Series B").is_some());
+    }
+
+    #[test]
+    fn removing_missing_series() {
+      let mut timeline = Timeline::new();
+
+      timeline.add_series(Series::new(String::from("Series A")));
+
+      assert!(!timeline.remove_series("Series B"));
+      assert_eq!(timeline.series.len(), 1);
+    }
+
+    #[test]
+    fn clearing_all_series() {
+      let mut timeline = Timeline::new();
+
+      timeline.add_series(Series::new(String::from("Series A")));
+      timeline.add_series(Series::new(String::from("Series B")));
+
+      timeline.clear();
+
+      assert_eq!(timeline.series.len(), 0);
+    }
   }
 }
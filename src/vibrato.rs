@@ -0,0 +1,165 @@
+// Vibrato (periodic pitch modulation) measured over a sequence of detected pitches.
+
+use super::pitch_detector::Pitch;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+// Fewer pitches than this can't reliably show a full oscillation.
+pub const MIN_PITCHES_FOR_VIBRATO: usize = 8;
+
+// Anything shallower is treated as a steady pitch.
+const MIN_VIBRATO_DEPTH_CENTS: f32 = 1.0;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Vibrato {
+  // Oscillations per second.
+  pub rate_hz: f32,
+  // Deviation either side of the centre pitch, i.e. half the peak-to-peak swing.
+  pub depth_cents: f32,
+}
+
+// Each pitch's offset in cents from the straight line best fitting the sequence, so a slow glide
+// isn't mistaken for part of the oscillation.
+fn detrended_cents(pitches: &[Pitch]) -> Vec<f32> {
+  let mean_frequency = pitches.iter().map(|p| p.frequency).sum::<f32>() / pitches.len() as f32;
+  let cents: Vec<f32> = pitches
+    .iter()
+    .map(|p| 1200.0 * (p.frequency / mean_frequency).log2())
+    .collect();
+
+  let n = pitches.len() as f32;
+  let mean_t = pitches.iter().map(|p| p.t).sum::<f32>() / n;
+  let mean_cents = cents.iter().sum::<f32>() / n;
+
+  let mut covariance = 0.0;
+  let mut variance = 0.0;
+  for (pitch, c) in pitches.iter().zip(cents.iter()) {
+    covariance += (pitch.t - mean_t) * (c - mean_cents);
+    variance += (pitch.t - mean_t) * (pitch.t - mean_t);
+  }
+  let slope = if variance > 0.0 {
+    covariance / variance
+  } else {
+    0.0
+  };
+
+  pitches
+    .iter()
+    .zip(cents.iter())
+    .map(|(pitch, c)| c - mean_cents - slope * (pitch.t - mean_t))
+    .collect()
+}
+
+// Measures the dominant oscillation of the pitches' frequencies. Pitches must be in time order
+// and from a single sustained note. Returns None if there are too few pitches or no oscillation.
+pub fn analyze_vibrato(pitches: &[Pitch]) -> Option<Vibrato> {
+  if pitches.len() < MIN_PITCHES_FOR_VIBRATO {
+    return None;
+  }
+
+  let cents = detrended_cents(pitches);
+
+  // Times at which the detrended pitch crosses the centre, interpolated between pitches.
+  let mut crossings = vec![];
+  for i in 1..cents.len() {
+    let (previous, current) = (cents[i - 1], cents[i]);
+    if (previous < 0.0) != (current < 0.0) {
+      let fraction = previous / (previous - current);
+      crossings.push(pitches[i - 1].t + fraction * (pitches[i].t - pitches[i - 1].t));
+    }
+  }
+
+  // Two crossings per oscillation, so at least three are needed to span a full one.
+  if crossings.len() < 3 {
+    return None;
+  }
+
+  let first_crossing = crossings[0];
+  let last_crossing = crossings[crossings.len() - 1];
+  let rate_hz = (crossings.len() - 1) as f32 / (2.0 * (last_crossing - first_crossing));
+
+  // Average the extreme of each half oscillation between crossings. Any remaining offset from
+  // detrending raises alternate peaks and lowers the others, so it cancels out.
+  let half_cycle_peaks: Vec<f32> = crossings
+    .windows(2)
+    .map(|span| {
+      pitches
+        .iter()
+        .zip(cents.iter())
+        .filter(|(pitch, _)| pitch.t > span[0] && pitch.t < span[1])
+        .fold(0.0_f32, |peak, (_, c)| peak.max(c.abs()))
+    })
+    .collect();
+  let depth_cents = half_cycle_peaks.iter().sum::<f32>() / half_cycle_peaks.len() as f32;
+
+  if depth_cents < MIN_VIBRATO_DEPTH_CENTS {
+    return None;
+  }
+
+  Some(Vibrato {
+    rate_hz,
+    depth_cents,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A second of pitches, one per 512 sample hop at 48kHz, for the given frequency over time.
+  fn pitches_from(frequency_at: impl Fn(f32) -> f32) -> Vec<Pitch> {
+    (0..94)
+      .map(|i| {
+        let t = i as f32 * 512.0 / 48000.0;
+        Pitch {
+          t,
+          frequency: frequency_at(t),
+          ..Default::default()
+        }
+      })
+      .collect()
+  }
+
+  fn modulated(rate_hz: f32, depth_cents: f32) -> impl Fn(f32) -> f32 {
+    move |t| {
+      let cents = depth_cents * (2.0 * std::f32::consts::PI * rate_hz * t).sin();
+      440.0 * 2.0_f32.powf(cents / 1200.0)
+    }
+  }
+
+  #[test]
+  fn measures_rate_and_depth() {
+    let vibrato = analyze_vibrato(&pitches_from(modulated(5.0, 30.0))).unwrap();
+
+    assert!((vibrato.rate_hz - 5.0).abs() < 0.25);
+    assert!((vibrato.depth_cents - 30.0).abs() < 2.0);
+  }
+
+  #[test]
+  fn ignores_a_slow_glide() {
+    let glide = modulated(5.0, 30.0);
+    let vibrato = analyze_vibrato(&pitches_from(|t| {
+      glide(t) * 2.0_f32.powf(50.0 * t / 1200.0)
+    }))
+    .unwrap();
+
+    assert!((vibrato.rate_hz - 5.0).abs() < 0.25);
+    assert!((vibrato.depth_cents - 30.0).abs() < 2.0);
+  }
+
+  #[test]
+  fn none_for_steady_pitch() {
+    assert_eq!(analyze_vibrato(&pitches_from(|_| 440.0)), None);
+  }
+
+  #[test]
+  fn none_for_too_few_pitches() {
+    let pitches = pitches_from(modulated(5.0, 30.0));
+
+    assert_eq!(
+      analyze_vibrato(&pitches[0..MIN_PITCHES_FOR_VIBRATO - 1]),
+      None
+    );
+  }
+}
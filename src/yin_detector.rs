@@ -0,0 +1,165 @@
+use pitch_detection::{Pitch, PitchDetector, PitchDetectorHistory};
+
+// Dips in the cumulative mean normalized difference below this are taken as the period. Lower
+// values favour the true fundamental over octave errors at the cost of missing noisy pitches.
+const DIP_THRESHOLD: f32 = 0.15;
+
+// YIN pitch detector (de Cheveigné & Kawahara, 2002).
+pub struct YinDetector {
+  size: usize,
+  // Working memory holding the difference function, then its cumulative mean normalized form.
+  cmnd: Vec<f32>,
+}
+
+impl YinDetector {
+  pub fn new(size: usize) -> YinDetector {
+    YinDetector {
+      size,
+      cmnd: vec![0.0; size / 2],
+    }
+  }
+
+  fn difference(&mut self, signal: &[f32]) {
+    let half = self.cmnd.len();
+
+    for tau in 0..half {
+      self.cmnd[tau] = (0..half)
+        .map(|j| {
+          let delta = signal[j] - signal[j + tau];
+          delta * delta
+        })
+        .sum();
+    }
+  }
+
+  fn cumulative_mean_normalize(&mut self) {
+    self.cmnd[0] = 1.0;
+
+    let mut running_sum = 0.0;
+    for tau in 1..self.cmnd.len() {
+      running_sum += self.cmnd[tau];
+      self.cmnd[tau] = match running_sum > 0.0 {
+        true => self.cmnd[tau] * tau as f32 / running_sum,
+        false => 1.0,
+      };
+    }
+  }
+
+  // The first dip below the threshold, followed down to its local minimum. Falls back to the
+  // global minimum if nothing dips below the threshold.
+  fn best_period(&self) -> Option<usize> {
+    let cmnd = &self.cmnd;
+
+    let mut tau = 2;
+    while tau < cmnd.len() {
+      if cmnd[tau] < DIP_THRESHOLD {
+        while tau + 1 < cmnd.len() && cmnd[tau + 1] < cmnd[tau] {
+          tau += 1;
+        }
+        return Some(tau);
+      }
+      tau += 1;
+    }
+
+    (2..cmnd.len()).min_by(|a, b| cmnd[*a].partial_cmp(&cmnd[*b]).unwrap())
+  }
+
+  // Refines the period to sub-sample accuracy by fitting a parabola through the neighbouring
+  // values.
+  fn interpolate(&self, tau: usize) -> f32 {
+    if tau < 1 || tau + 1 >= self.cmnd.len() {
+      return tau as f32;
+    }
+
+    let (prev, curr, next) = (self.cmnd[tau - 1], self.cmnd[tau], self.cmnd[tau + 1]);
+    let denominator = prev - 2.0 * curr + next;
+
+    match denominator.abs() > f32::EPSILON {
+      true => tau as f32 + 0.5 * (prev - next) / denominator,
+      false => tau as f32,
+    }
+  }
+}
+
+impl PitchDetector<f32> for YinDetector {
+  fn get_pitch(
+    &mut self,
+    signal: &[f32],
+    sample_rate: usize,
+    power_threshold: f32,
+    clarity_threshold: f32,
+    _history: Option<PitchDetectorHistory>,
+  ) -> Option<Pitch<f32>> {
+    if signal.len() < self.size {
+      return None;
+    }
+
+    let power: f32 = signal[..self.size].iter().map(|s| s * s).sum();
+    if power < power_threshold {
+      return None;
+    }
+
+    self.difference(signal);
+    self.cumulative_mean_normalize();
+
+    let tau = self.best_period()?;
+    let clarity = 1.0 - self.cmnd[tau];
+    if clarity < clarity_threshold {
+      return None;
+    }
+
+    Some(Pitch {
+      frequency: sample_rate as f32 / self.interpolate(tau),
+      clarity,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils;
+
+  const SAMPLE_RATE: usize = 48000;
+  const WINDOW: usize = 2048;
+
+  fn detect(freq: f32) -> Option<Pitch<f32>> {
+    let signal = test_utils::sin_signal(freq, WINDOW, SAMPLE_RATE);
+
+    YinDetector::new(WINDOW).get_pitch(&signal, SAMPLE_RATE, 0.25, 0.6, None)
+  }
+
+  #[test]
+  fn detects_110_hz() {
+    let pitch = detect(110.0).unwrap();
+
+    assert!((pitch.frequency - 110.0).abs() < 1.0);
+    assert!(pitch.clarity > 0.8);
+  }
+
+  #[test]
+  fn detects_220_hz() {
+    let pitch = detect(220.0).unwrap();
+
+    assert!((pitch.frequency - 220.0).abs() < 1.0);
+    assert!(pitch.clarity > 0.8);
+  }
+
+  #[test]
+  fn no_pitch_for_silence() {
+    let signal = test_utils::new_real_buffer(WINDOW);
+
+    assert!(YinDetector::new(WINDOW)
+      .get_pitch(&signal, SAMPLE_RATE, 0.25, 0.6, None)
+      .is_none());
+  }
+
+  #[test]
+  fn no_pitch_for_short_signal() {
+    let signal = test_utils::sin_signal(220.0, WINDOW / 2, SAMPLE_RATE);
+
+    assert!(YinDetector::new(WINDOW)
+      .get_pitch(&signal, SAMPLE_RATE, 0.25, 0.6, None)
+      .is_none());
+  }
+}
@@ -125,3 +125,45 @@ fn adding_data() {
 
   // print_detector_state(&detector);
 }
+
+#[wasm_bindgen_test]
+fn returns_latest_samples_as_typed_array() {
+  let mut processor = audio_samples_processor::AudioSamplesProcessor::new();
+
+  let samples: Vec<f32> = (0..256).map(|i| i as f32).collect();
+  processor.add_samples_chunk(samples.clone());
+
+  let latest = processor.get_latest_samples();
+
+  assert_eq!(latest.length(), 256);
+  assert_eq!(latest.to_vec(), samples);
+}
+
+#[wasm_bindgen_test]
+fn try_new_returns_error_for_oversized_window() {
+  let result = pitch_detector::PitchDetector::try_new(
+    String::from("McLeod"),
+    pitch_detector::make_params(16384, 48000, 0.25, 0.6),
+  );
+
+  let error = js_sys::Error::from(result.err().unwrap());
+  assert_eq!(error.name(), "window_too_large");
+  assert_eq!(
+    error.message(),
+    "window size exceeded maximum window size 8192"
+  );
+}
+
+#[wasm_bindgen_test]
+fn reads_back_detector_config() {
+  let detector = pitch_detector::PitchDetector::new(
+    String::from("YIN"),
+    pitch_detector::make_params(4096, 44100, 0.3, 0.7),
+  );
+
+  assert_eq!(detector.detector_type(), "YIN");
+  assert_eq!(detector.window(), 4096);
+  assert_eq!(detector.sample_rate(), 44100);
+  assert_eq!(detector.power_threshold(), 0.3);
+  assert_eq!(detector.clarity_threshold(), 0.7);
+}